@@ -0,0 +1,307 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log::*;
+use regex::Regex;
+use which::which;
+
+#[cfg(windows)]
+use winreg::{enums::HKEY_LOCAL_MACHINE, RegKey};
+
+use super::LaunchError;
+use crate::Error;
+
+/// The oldest major version of Chrome we'll use: that of the Chromium revision we download
+/// (`CUR_REV`), whose protocol we speak.
+pub const MIN_CHROME_VERSION: u32 = 74;
+
+/// Environment variable naming the Chrome executable `default_executable` should use, either
+/// as a path or as a program on the `PATH`.
+pub const CHROME_ENV: &str = "CHROME";
+
+/// Programs we look for on the `PATH`, in order of preference.
+const PROGRAMS: &[&str] = &[
+    "google-chrome-stable",
+    "google-chrome",
+    "chromium",
+    "chromium-browser",
+    "google-chrome-beta",
+];
+
+/// Where Chrome tends to be installed when it isn't on the `PATH`, including the wrappers snap
+/// and flatpak install.
+#[cfg(target_os = "linux")]
+const INSTALL_PATHS: &[&str] = &[
+    "/opt/google/chrome/chrome",
+    "/opt/google/chrome-beta/chrome",
+    "/usr/lib/chromium/chromium",
+    "/usr/lib/chromium-browser/chromium-browser",
+    "/snap/bin/chromium",
+    "/var/lib/snapd/snap/bin/chromium",
+    "/var/lib/flatpak/exports/bin/com.google.Chrome",
+    "/var/lib/flatpak/exports/bin/org.chromium.Chromium",
+];
+#[cfg(target_os = "macos")]
+const INSTALL_PATHS: &[&str] = &[
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    "/Applications/Google Chrome Beta.app/Contents/MacOS/Google Chrome Beta",
+];
+#[cfg(windows)]
+const INSTALL_PATHS: &[&str] = &[];
+
+/// Flatpak wrappers installed for the current user, relative to their home directory.
+#[cfg(target_os = "linux")]
+const USER_INSTALL_PATHS: &[&str] = &[
+    ".local/share/flatpak/exports/bin/com.google.Chrome",
+    ".local/share/flatpak/exports/bin/org.chromium.Chromium",
+];
+
+/// The version of a Chrome executable, e.g. `74.0.3729.169`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ChromeVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+    pub patch: u32,
+}
+
+impl ChromeVersion {
+    /// Finds the version in what `chrome --version` prints, e.g.
+    /// `Google Chrome 74.0.3729.169` or `Chromium 74.0.3729.0 snap`.
+    fn from_version_output(output: &str) -> Option<Self> {
+        let re = Regex::new(r"(\d+)\.(\d+)\.(\d+)\.(\d+)").unwrap();
+        let captures = re.captures(output)?;
+        let part = |i: usize| captures[i].parse::<u32>().ok();
+        Some(Self {
+            major: part(1)?,
+            minor: part(2)?,
+            build: part(3)?,
+            patch: part(4)?,
+        })
+    }
+}
+
+impl fmt::Display for ChromeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.build, self.patch
+        )
+    }
+}
+
+/// Asks the Chrome executable at `path` for its version, by running it with `--version`.
+///
+/// Chrome doesn't support this on Windows, where it opens a window instead.
+pub fn chrome_version<P: AsRef<Path>>(path: P) -> Result<ChromeVersion, Error> {
+    let path = path.as_ref();
+    let output = Command::new(path).arg("--version").output()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    ChromeVersion::from_version_output(&output).ok_or_else(|| {
+        LaunchError::UnrecognisedVersion {
+            path: path.display().to_string(),
+            output: output.trim().to_string(),
+        }
+        .into()
+    })
+}
+
+/// Finds a Chrome executable to launch, with a version of at least `MIN_CHROME_VERSION`.
+///
+/// We use the one named by the `CHROME` environment variable if it's set, and otherwise look
+/// for the first Chrome or Chromium among the commands in `$BROWSER`, the usual programs on the
+/// `PATH` and the usual install locations. Executables which are too old are skipped, with a
+/// `LaunchError::UnsupportedVersion` returned if there are no others.
+pub fn default_executable() -> Result<PathBuf, Error> {
+    if let Some(value) = env::var_os(CHROME_ENV) {
+        let path = resolve(&value).ok_or_else(|| LaunchError::InvalidExecutableEnv {
+            var: CHROME_ENV,
+            value: value.to_string_lossy().into_owned(),
+        })?;
+        check_version(&path)?;
+        return Ok(path);
+    }
+
+    let mut too_old = None;
+    for path in candidates() {
+        match check_version(&path) {
+            Ok(()) => return Ok(path),
+            Err(error) => {
+                warn!("Skipping {}: {}", path.display(), error);
+                too_old.get_or_insert(error);
+            }
+        }
+    }
+    Err(too_old.unwrap_or(LaunchError::NoExecutableFound).into())
+}
+
+/// Every Chrome executable we can find, in order of preference.
+fn candidates() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = env::var_os("BROWSER")
+        .map(|value| browser_env_programs(&value))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(resolve)
+        .collect();
+
+    paths.extend(PROGRAMS.iter().filter_map(|program| which(program).ok()));
+    paths.extend(INSTALL_PATHS.iter().map(PathBuf::from));
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(home) = env::var_os("HOME") {
+            paths.extend(
+                USER_INSTALL_PATHS
+                    .iter()
+                    .map(|path| Path::new(&home).join(path)),
+            );
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        paths.extend(get_chrome_path_from_registry());
+    }
+
+    let mut found: Vec<PathBuf> = vec![];
+    for path in paths {
+        if path.is_file() && !found.contains(&path) {
+            found.push(path);
+        }
+    }
+    found
+}
+
+/// The programs in `$BROWSER` (a list of commands, separated like the `PATH`) which look like
+/// Chrome or Chromium, without any arguments the commands pass them.
+fn browser_env_programs(value: &OsStr) -> Vec<PathBuf> {
+    env::split_paths(value)
+        .filter_map(|command| {
+            let program = command.to_str()?.split_whitespace().next()?.to_string();
+            let name = Path::new(&program).file_name()?.to_str()?.to_lowercase();
+            if name.contains("chrom") {
+                Some(PathBuf::from(program))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// `program` if it's a path to a file, and otherwise where it is on the `PATH`.
+fn resolve<P: AsRef<OsStr>>(program: P) -> Option<PathBuf> {
+    let program = Path::new(program.as_ref());
+    if program.components().count() > 1 {
+        Some(program.to_path_buf()).filter(|path| path.is_file())
+    } else {
+        which(program).ok()
+    }
+}
+
+fn check_version(path: &Path) -> Result<(), LaunchError> {
+    if cfg!(windows) {
+        return Ok(());
+    }
+    match chrome_version(path) {
+        Ok(version) if version.major < MIN_CHROME_VERSION => Err(LaunchError::UnsupportedVersion {
+            path: path.display().to_string(),
+            version: version.to_string(),
+            required: MIN_CHROME_VERSION,
+        }),
+        Ok(version) => {
+            info!("Found Chrome {} at {}", version, path.display());
+            Ok(())
+        }
+        Err(error) => {
+            warn!(
+                "Couldn't check the version of {}: {}",
+                path.display(),
+                error
+            );
+            Ok(())
+        }
+    }
+}
+
+#[cfg(windows)]
+fn get_chrome_path_from_registry() -> Option<PathBuf> {
+    RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\App Paths\\chrome.exe")
+        .and_then(|key| key.get_value::<String, _>(""))
+        .map(PathBuf::from)
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_version_output() {
+        let version = ChromeVersion::from_version_output("Google Chrome 74.0.3729.169 \n");
+        assert_eq!(
+            Some(ChromeVersion {
+                major: 74,
+                minor: 0,
+                build: 3729,
+                patch: 169,
+            }),
+            version
+        );
+        assert_eq!(
+            "120.0.6099.71",
+            ChromeVersion::from_version_output("Chromium 120.0.6099.71 snap")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(None, ChromeVersion::from_version_output("Firefox 66.0"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn picks_chrome_out_of_browser_env() {
+        let programs = browser_env_programs(OsStr::new(
+            "firefox:/usr/bin/google-chrome --incognito %s:chromium-browser:xdg-open",
+        ));
+        assert_eq!(
+            vec![
+                PathBuf::from("/usr/bin/google-chrome"),
+                PathBuf::from("chromium-browser")
+            ],
+            programs
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_old_versions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let fake_chrome = |name: &str, version: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, format!("#!/bin/sh\necho 'Chromium {}'\n", version)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            path
+        };
+
+        let old = fake_chrome("old", "73.0.3683.0");
+        assert_eq!(73, chrome_version(&old).unwrap().major);
+        match check_version(&old) {
+            Err(LaunchError::UnsupportedVersion { version, .. }) => {
+                assert_eq!("73.0.3683.0", version)
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+
+        let new = fake_chrome("new", "74.0.3729.169");
+        check_version(&new).unwrap();
+        assert_eq!(Some(new.clone()), resolve(&new));
+        assert_eq!(None, resolve(dir.path().join("missing")));
+    }
+}
@@ -1,23 +1,32 @@
 use directories::ProjectDirs;
-use failure::{format_err, Error};
+use failure::Fail;
+use fs2::FileExt;
+#[cfg(feature = "progress-bars")]
 use indicatif::{ProgressBar, ProgressStyle};
 use log::*;
 use reqwest::{self, header::CONTENT_LENGTH};
-use zip;
+use sha2::{Digest, Sha256};
+use zip::{self, result::ZipError};
 
 use std::{
     env,
     fs::{self, File, OpenOptions},
-    io::{self, BufWriter, Write},
+    io::{self, BufWriter, Read, Write},
     path::{Path, PathBuf},
     str::FromStr,
 };
 
+use crate::Error;
+
 pub const CUR_REV: &str = "634997";
 
 const APP_NAME: &str = "headless-chrome";
 const DEFAULT_HOST: &str = "https://storage.googleapis.com";
 
+/// Environment variable naming a host to download Chromium from instead of `DEFAULT_HOST`,
+/// unless one is given explicitly.
+pub const DOWNLOAD_HOST_ENV: &str = "HEADLESS_CHROME_DOWNLOAD_HOST";
+
 #[cfg(target_os = "linux")]
 const PLATFORM: &str = "linux";
 #[cfg(target_os = "macos")]
@@ -25,16 +34,167 @@ const PLATFORM: &str = "mac";
 #[cfg(windows)]
 const PLATFORM: &str = "win";
 
+#[cfg(target_os = "linux")]
+const SNAPSHOTS_PLATFORM: &str = "Linux_x64";
+#[cfg(target_os = "macos")]
+const SNAPSHOTS_PLATFORM: &str = "Mac";
+#[cfg(windows)]
+const SNAPSHOTS_PLATFORM: &str = "Win_x64";
+
+/// Written into a revision's directory once it's been completely installed, holding the SHA-256
+/// of the archive it came from.
+const INSTALLED_MARKER: &str = ".installed";
+
+/// Why we couldn't download or install a revision of Chromium.
+#[derive(Debug, Fail)]
+pub enum FetchError {
+    #[fail(display = "Couldn't find a directory to download Chromium into")]
+    NoDataDir,
+    #[fail(display = "Unexpected file in the download directory: {}", _0)]
+    UnexpectedFile(String),
+    #[fail(display = "Not a valid Chromium revision: {}", _0)]
+    InvalidRevision(String),
+    #[fail(display = "The server didn't say how big {} is", _0)]
+    MissingContentLength(String),
+    #[fail(display = "Not a valid file URL: {}", _0)]
+    InvalidFileUrl(String),
+    #[fail(
+        display = "Checksum of the downloaded archive didn't match: expected {}, got {}",
+        expected, actual
+    )]
+    ChecksumMismatch { expected: String, actual: String },
+    #[fail(display = "The checksum manifest doesn't list {}", _0)]
+    NotInManifest(String),
+    #[fail(display = "Downloading Chromium failed: {}", _0)]
+    Download(#[cause] reqwest::Error),
+    #[fail(display = "Extracting Chromium failed: {}", _0)]
+    Extract(#[cause] ZipError),
+    #[fail(display = "{}", _0)]
+    Io(#[cause] io::Error),
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(error: reqwest::Error) -> Self {
+        FetchError::Download(error)
+    }
+}
+
+impl From<ZipError> for FetchError {
+    fn from(error: ZipError) -> Self {
+        FetchError::Extract(error)
+    }
+}
+
+impl From<io::Error> for FetchError {
+    fn from(error: io::Error) -> Self {
+        FetchError::Io(error)
+    }
+}
+
+/// Something that happened while a `Fetcher` was installing a revision.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchEvent<'a> {
+    DownloadStarted {
+        url: &'a str,
+        total_bytes: u64,
+    },
+    /// How much has been downloaded so far.
+    Downloaded {
+        bytes: u64,
+    },
+    DownloadFinished,
+    ExtractionStarted {
+        total_files: u64,
+    },
+    /// How many files have been extracted so far.
+    Extracted {
+        files: u64,
+    },
+    ExtractionFinished,
+}
+
+/// Told how a `Fetcher` is getting on, e.g. to show it in a UI or log it.
+///
+/// Implemented by closures taking a `&FetchEvent`, as well as by `ProgressBars`, which a
+/// `Fetcher` uses unless given another with `with_progress`.
+pub trait FetchProgress: Send + Sync {
+    fn report(&self, event: &FetchEvent);
+}
+
+impl<F> FetchProgress for F
+where
+    F: Fn(&FetchEvent) + Send + Sync,
+{
+    fn report(&self, event: &FetchEvent) {
+        self(event);
+    }
+}
+
+/// Shows progress bars on the terminal, except when the `IN_CI` environment variable is
+/// `true`.
+#[cfg(feature = "progress-bars")]
+#[derive(Default)]
+pub struct ProgressBars {
+    bar: std::sync::Mutex<Option<ProgressBar>>,
+}
+
+#[cfg(feature = "progress-bars")]
+impl ProgressBars {
+    fn start(&self, len: u64, template: &str) {
+        let bar = ProgressBar::new(len);
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template(template)
+                .progress_chars("#>-"),
+        );
+        if in_ci() {
+            bar.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+        }
+        *self.bar.lock().unwrap() = Some(bar);
+    }
+
+    fn set_position(&self, position: u64) {
+        if let Some(bar) = self.bar.lock().unwrap().as_ref() {
+            bar.set_position(position);
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(bar) = self.bar.lock().unwrap().take() {
+            bar.finish();
+        }
+    }
+}
+
+#[cfg(feature = "progress-bars")]
+impl FetchProgress for ProgressBars {
+    fn report(&self, event: &FetchEvent) {
+        match *event {
+            FetchEvent::DownloadStarted { total_bytes, .. } => self.start(
+                total_bytes,
+                "[{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})",
+            ),
+            FetchEvent::ExtractionStarted { total_files } => self.start(
+                total_files,
+                "[{elapsed_precise}] [{wide_bar}] ({pos}/{len})",
+            ),
+            FetchEvent::Downloaded { bytes } => self.set_position(bytes),
+            FetchEvent::Extracted { files } => self.set_position(files),
+            FetchEvent::DownloadFinished | FetchEvent::ExtractionFinished => self.finish(),
+        }
+    }
+}
+
 struct DownloadProgress<W, F> {
     inner: W,
-    bytes_read: usize,
+    bytes_read: u64,
     progress: F,
 }
 
 impl<W, F> DownloadProgress<W, F>
 where
     W: Write,
-    F: FnMut(usize),
+    F: FnMut(u64),
 {
     pub fn new(inner: W, progress: F) -> Self {
         Self {
@@ -45,10 +205,10 @@ where
     }
 }
 
-impl<W: Write, F: FnMut(usize)> Write for DownloadProgress<W, F> {
+impl<W: Write, F: FnMut(u64)> Write for DownloadProgress<W, F> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.inner.write(buf).map(|n| {
-            self.bytes_read += n;
+            self.bytes_read += n as u64;
             (self.progress)(self.bytes_read);
             n
         })
@@ -58,55 +218,179 @@ impl<W: Write, F: FnMut(usize)> Write for DownloadProgress<W, F> {
     }
 }
 
-pub struct Fetcher<'a> {
-    rev: &'a str,
-    dirs: ProjectDirs,
+/// A directory revisions of Chromium are installed in, by default our XDG data directory (e.g.
+/// `~/.local/share/headless-chrome`).
+///
+/// Revisions are only installed or removed while holding a lock on them, so that processes
+/// sharing the directory, like parallel test runs, wait for each other rather than all
+/// downloading the same revision.
+///
+/// ```rust,no_run
+/// # use headless_chrome::Error;
+/// # fn main() -> Result<(), Error> {
+/// #
+/// use headless_chrome::browser::{ChromeCache, CUR_REV};
+///
+/// let cache = ChromeCache::new()?;
+/// for revision in cache.revisions()? {
+///     println!("{}: {} bytes", revision.revision, revision.size);
+/// }
+/// cache.remove_all_except(CUR_REV)?;
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChromeCache {
+    dir: PathBuf,
 }
 
-impl<'a> Fetcher<'a> {
-    pub fn new(rev: &'a str) -> Result<Self, Error> {
-        let dirs = get_project_dirs()?;
+/// A revision of Chromium installed in a `ChromeCache`.
+#[derive(Debug, Clone)]
+pub struct CachedRevision {
+    pub revision: String,
+    /// The directory it's installed in.
+    pub path: PathBuf,
+    /// How much disk space it takes up, in bytes.
+    pub size: u64,
+}
+
+impl ChromeCache {
+    /// The cache in our XDG data directory.
+    pub fn new() -> Result<Self, Error> {
+        Self::in_dir(get_project_dirs()?.data_dir())
+    }
+
+    /// A cache in the given directory, which is created if need be.
+    pub fn in_dir<P: Into<PathBuf>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.into();
         info!(
-            "Creating XDG_DATA_DIR if it doesn't exist: {}",
-            dirs.data_dir().display()
+            "Creating cache directory if it doesn't exist: {}",
+            dir.display()
         );
-        fs::create_dir_all(dirs.data_dir())?;
-        Ok(Self { rev, dirs })
+        fs::create_dir_all(&dir).map_err(FetchError::from)?;
+        Ok(Self { dir })
     }
 
-    fn local_revisions(&self) -> Result<Vec<String>, Error> {
-        trace!(
-            "Enumerating contents of XDG_DATA_DIR: {}",
-            self.dirs.data_dir().display()
-        );
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The revisions which are completely installed, in order.
+    pub fn revisions(&self) -> Result<Vec<CachedRevision>, Error> {
         let mut revisions = Vec::new();
-        for entry in fs::read_dir(self.dirs.data_dir())? {
+        for rev in self.revision_dirs()? {
+            if !self.is_installed(&rev) {
+                warn!("Ignoring incomplete install of revision {}", rev);
+                continue;
+            }
+            let path = self.base_path(&rev);
+            revisions.push(CachedRevision {
+                size: dir_size(&path).map_err(FetchError::from)?,
+                revision: rev,
+                path,
+            });
+        }
+        Ok(revisions)
+    }
+
+    /// The path to the executable of `rev`, if it's installed.
+    pub fn executable(&self, rev: &str) -> Option<PathBuf> {
+        if self.is_installed(rev) {
+            self.chrome_path(rev).ok()
+        } else {
+            None
+        }
+    }
+
+    /// Remove `rev`, whether or not it's completely installed, returning whether there was
+    /// anything to remove.
+    pub fn remove(&self, rev: &str) -> Result<bool, Error> {
+        let _lock = self.lock(rev)?;
+        let path = self.base_path(rev);
+        if !path.exists() {
+            return Ok(false);
+        }
+        info!("Removing {}", path.display());
+        fs::remove_dir_all(&path).map_err(FetchError::from)?;
+        Ok(true)
+    }
+
+    /// Remove every revision but `rev` (e.g. `CUR_REV`), returning the ones removed.
+    pub fn remove_all_except(&self, rev: &str) -> Result<Vec<String>, Error> {
+        let mut removed = Vec::new();
+        for other in self.revision_dirs()? {
+            if other != rev && self.remove(&other)? {
+                removed.push(other);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// The revisions there's a directory for, whether or not they're completely installed.
+    fn revision_dirs(&self) -> Result<Vec<String>, FetchError> {
+        trace!("Enumerating contents of {}", self.dir.display());
+        let mut revisions = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
             let entry = entry?;
             let path = entry.path();
             if path.is_dir() {
                 let filename = path
                     .file_name()
-                    .ok_or_else(|| format_err!("Failed to turn into OsStr"))?
-                    .to_str()
-                    .ok_or_else(|| format_err!("Failed conversion to UTF8"))?
+                    .and_then(|file_name| file_name.to_str())
+                    .ok_or_else(|| FetchError::UnexpectedFile(path.to_string_lossy().into_owned()))?
                     .split('-')
                     .collect::<Vec<_>>();
                 if filename.len() == 2 && filename[0] == PLATFORM {
-                    let rev = filename[1].to_string();
-                    revisions.push(rev)
+                    revisions.push(filename[1].to_string());
                 }
             }
         }
+        // numerically, so 99999 comes before 634997, with anything else by name
+        revisions.sort_by(|a, b| (a.parse::<u64>().ok(), a).cmp(&(b.parse::<u64>().ok(), b)));
         Ok(revisions)
     }
 
+    /// Whether `rev` was completely installed, and its executable is still there.
+    ///
+    /// Installs from before we marked them as complete are taken to be, as long as they have
+    /// an executable, and are marked as such (with no checksum) from then on.
+    fn is_installed(&self, rev: &str) -> bool {
+        let has_executable = match self.chrome_path(rev) {
+            Ok(path) => path.is_file(),
+            Err(_) => false,
+        };
+        if !has_executable {
+            return false;
+        }
+        let marker = self.base_path(rev).join(INSTALLED_MARKER);
+        if !marker.is_file() {
+            info!(
+                "Taking revision {} to be installed by an older version",
+                rev
+            );
+            if let Err(error) = fs::write(&marker, "") {
+                warn!("Couldn't mark revision {} as installed: {}", rev, error);
+            }
+        }
+        true
+    }
+
+    /// Blocks until no other process (or thread) holds the lock on `rev`, and takes it until
+    /// the returned file is dropped.
+    fn lock(&self, rev: &str) -> Result<File, FetchError> {
+        let path = self.dir.join(format!(".{}-{}.lock", PLATFORM, rev));
+        let file = OpenOptions::new().create(true).write(true).open(&path)?;
+        trace!("Waiting for lock on {}", path.display());
+        file.lock_exclusive()?;
+        Ok(file)
+    }
+
     fn base_path(&self, rev: &str) -> PathBuf {
-        let mut path = self.dirs.data_dir().to_path_buf();
-        path.push(format!("{}-{}", PLATFORM, rev));
-        path
+        self.dir.join(format!("{}-{}", PLATFORM, rev))
     }
 
-    fn chrome_path(&self, rev: &str) -> Result<PathBuf, Error> {
+    fn chrome_path(&self, rev: &str) -> Result<PathBuf, FetchError> {
         let mut path = self.base_path(rev);
         path.push(archive_name(rev)?);
 
@@ -128,63 +412,198 @@ impl<'a> Fetcher<'a> {
 
         Ok(path)
     }
+}
+
+/// Downloads and installs revisions of Chromium into a `ChromeCache`.
+///
+/// Revisions are downloaded from `https://storage.googleapis.com` unless another host is given
+/// with `with_host` or the `HEADLESS_CHROME_DOWNLOAD_HOST` environment variable. A mirror
+/// should have the same layout, i.e. serve e.g.
+/// `chromium-browser-snapshots/Linux_x64/634997/chrome-linux.zip`, and can be a `file://` URL
+/// of a local directory.
+///
+/// Archives are checked against a SHA-256 given with `with_sha256` or listed in a manifest
+/// given with `with_manifest`, when there is one, and unpacked into a temporary directory which
+/// is only moved into place once complete. Revisions left half-installed, e.g. by an
+/// interrupted download, are installed again.
+///
+/// Progress is shown with `ProgressBars` (with the `progress-bars` feature, which is on by
+/// default) unless reported elsewhere with `with_progress`.
+pub struct Fetcher<'a> {
+    rev: &'a str,
+    cache: ChromeCache,
+    host: String,
+    sha256: Option<String>,
+    manifest: Option<String>,
+    progress: Box<dyn FetchProgress>,
+}
+
+impl<'a> Fetcher<'a> {
+    pub fn new(rev: &'a str) -> Result<Self, Error> {
+        Ok(Self::with_cache(rev, ChromeCache::new()?))
+    }
+
+    /// Install into `cache` rather than the one in our XDG data directory.
+    pub fn with_cache(rev: &'a str, cache: ChromeCache) -> Self {
+        let host = env::var(DOWNLOAD_HOST_ENV).unwrap_or_else(|_| DEFAULT_HOST.to_string());
+        Self {
+            rev,
+            cache,
+            host,
+            sha256: None,
+            manifest: None,
+            progress: default_progress(),
+        }
+    }
+
+    /// Download from the given host (e.g. `http://mirror.internal:8080` or
+    /// `file:///srv/chromium-mirror`) instead.
+    pub fn with_host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    /// Only install an archive with this SHA-256, given in hex.
+    pub fn with_sha256(mut self, sha256: &str) -> Self {
+        self.sha256 = Some(sha256.to_lowercase());
+        self
+    }
+
+    /// Look up the SHA-256 archives should have in the manifest at this URL (which can be a
+    /// `file://` URL), in the format written by `sha256sum`, e.g.
+    ///
+    /// ```text
+    /// 3f1a...c9e2  chromium-browser-snapshots/Linux_x64/634997/chrome-linux.zip
+    /// ```
+    ///
+    /// with archives listed by their path on the host. Ignored if `with_sha256` is given.
+    pub fn with_manifest(mut self, url: &str) -> Self {
+        self.manifest = Some(url.to_string());
+        self
+    }
+
+    /// Report progress to `progress` instead, e.g.
+    ///
+    /// ```rust,no_run
+    /// # use headless_chrome::browser::{FetchEvent, Fetcher, CUR_REV};
+    /// # fn main() -> Result<(), headless_chrome::Error> {
+    /// let fetcher = Fetcher::new(CUR_REV)?.with_progress(|event: &FetchEvent| {
+    ///     if let FetchEvent::Downloaded { bytes } = event {
+    ///         log::info!("Downloaded {} bytes of Chromium", bytes);
+    ///     }
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_progress<P: FetchProgress + 'static>(mut self, progress: P) -> Self {
+        self.progress = Box::new(progress);
+        self
+    }
 
     pub fn run(&self) -> Result<PathBuf, Error> {
-        let revisions = self.local_revisions()?;
-        if let Some(revision) = revisions.into_iter().find(|r| r == self.rev) {
+        Ok(self.fetch()?)
+    }
+
+    fn fetch(&self) -> Result<PathBuf, FetchError> {
+        if let Some(path) = self.cache.executable(self.rev) {
             info!("No need to download, we have the correct revision");
-            return Ok(self.chrome_path(&revision)?);
+            return Ok(path);
+        }
+        let _lock = self.cache.lock(self.rev)?;
+        if let Some(path) = self.cache.executable(self.rev) {
+            info!("Another process installed the revision while we waited for it");
+            return Ok(path);
         }
 
-        let url = dl_url(self.rev)?;
+        let url = dl_url(&self.host, self.rev)?;
         info!("Chrome download url: {}", url);
-        let total = get_size(&url)?;
-        info!("Total size of download: {}", total);
-        let path = self.base_path(self.rev).with_extension("zip");
+        // deleted once dropped, however we get on
+        let archive = tempfile::Builder::new()
+            .prefix(".download-")
+            .suffix(".zip")
+            .tempfile_in(&self.cache.dir)?;
+        download(&url, archive.path(), &*self.progress)?;
+        self.install(archive.path())?;
 
-        info!("Creating file for download: {}", &path.display());
-        let file = OpenOptions::new().create(true).write(true).open(&path)?;
+        self.cache.chrome_path(self.rev)
+    }
 
-        let pb = ProgressBar::new(total);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template(
-                    "[{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})",
-                )
-                .progress_chars("#>-"),
-        );
-        let mut dest = DownloadProgress::new(file, |n| {
-            if !in_ci() {
-                pb.set_position(n as u64);
-            }
-        });
+    /// Install our revision from a zip archive downloaded beforehand (e.g.
+    /// `chrome-linux.zip`), returning the path to its executable.
+    ///
+    /// The archive itself is left where it is.
+    pub fn unzip<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, Error> {
+        let _lock = self.cache.lock(self.rev)?;
+        self.install(path.as_ref())?;
+        Ok(self.cache.chrome_path(self.rev)?)
+    }
 
-        let mut resp = reqwest::get(&url)?;
-        io::copy(&mut resp, &mut dest)?;
+    /// Verifies the archive at `path` and extracts it into a temporary directory, which then
+    /// replaces whatever was installed for our revision before. Expects the caller to hold the
+    /// lock on it.
+    fn install(&self, path: &Path) -> Result<(), FetchError> {
+        let actual = sha256(path)?;
+        match self.expected_sha256()? {
+            Some(expected) => {
+                if expected != actual {
+                    return Err(FetchError::ChecksumMismatch { expected, actual });
+                }
+                info!("Verified SHA-256 of {}: {}", path.display(), actual);
+            }
+            None => info!("No checksum to verify {} against", path.display()),
+        }
 
-        pb.finish();
+        let staging = tempfile::Builder::new()
+            .prefix(".install-")
+            .tempdir_in(&self.cache.dir)?;
+        self.extract(path, staging.path())?;
+        fs::write(staging.path().join(INSTALLED_MARKER), &actual)?;
 
-        self.unzip(&path)?;
+        let base_path = self.cache.base_path(self.rev);
+        if base_path.exists() {
+            warn!("Replacing incomplete install at {}", base_path.display());
+            fs::remove_dir_all(&base_path)?;
+        }
+        if let Err(error) = fs::rename(staging.path(), &base_path) {
+            // an older version of us, which doesn't lock, may have beaten us to it
+            if !self.cache.is_installed(self.rev) {
+                return Err(error.into());
+            }
+        }
+        Ok(())
+    }
 
-        Ok(self.chrome_path(self.rev)?)
+    fn expected_sha256(&self) -> Result<Option<String>, FetchError> {
+        if let Some(sha256) = &self.sha256 {
+            return Ok(Some(sha256.clone()));
+        }
+        let manifest = match &self.manifest {
+            Some(url) => read_to_string(url)?,
+            None => return Ok(None),
+        };
+        let archive = archive_path(self.rev)?;
+        manifest
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                Some((fields.next()?, fields.next()?))
+            })
+            .find(|(_, file)| file.trim_start_matches('*').trim_start_matches("./") == archive)
+            .map(|(sha256, _)| Some(sha256.to_lowercase()))
+            .ok_or_else(|| FetchError::NotInManifest(archive))
     }
 
-    pub fn unzip<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
-        let mut archive = zip::ZipArchive::new(File::open(path.as_ref())?)?;
-        let extract_path = self.base_path(self.rev);
-        fs::create_dir_all(&extract_path)?;
+    fn extract(&self, path: &Path, extract_path: &Path) -> Result<(), FetchError> {
+        let mut archive = zip::ZipArchive::new(File::open(path)?)?;
 
         info!("Extracting: {}", extract_path.display());
-        let pb = ProgressBar::new(archive.len() as u64);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] [{wide_bar}] ({pos}/{len})")
-                .progress_chars("#>-"),
-        );
+        self.progress.report(&FetchEvent::ExtractionStarted {
+            total_files: archive.len() as u64,
+        });
 
         for i in 0..archive.len() {
             let mut file = archive.by_index(i)?;
-            let mut out_path = extract_path.clone();
+            let mut out_path = extract_path.to_path_buf();
             out_path.push(file.sanitized_name().as_path());
 
             let comment = file.comment();
@@ -213,10 +632,6 @@ impl<'a> Fetcher<'a> {
                 }
                 let mut out_file = BufWriter::new(File::create(&out_path)?);
                 io::copy(&mut file, &mut out_file)?;
-
-                if !in_ci() {
-                    pb.set_position(i as u64);
-                }
             }
             // Get and Set permissions
             #[cfg(unix)]
@@ -227,75 +642,139 @@ impl<'a> Fetcher<'a> {
                     fs::set_permissions(&out_path, fs::Permissions::from_mode(mode)).unwrap();
                 }
             }
-        }
 
-        pb.finish();
-        info!("Cleaning up");
-        if fs::remove_file(&path).is_err() {
-            info!("Failed to delete zip");
-            return Ok(());
+            self.progress.report(&FetchEvent::Extracted {
+                files: i as u64 + 1,
+            });
         }
 
+        self.progress.report(&FetchEvent::ExtractionFinished);
         Ok(())
     }
 }
 
-fn get_size<U: AsRef<str>>(url: U) -> Result<u64, Error> {
-    let client = reqwest::Client::new();
-    let response = client.head(url.as_ref()).send()?;
-    match response.headers().get(CONTENT_LENGTH) {
-        Some(len) => {
-            let length = u64::from_str(len.to_str()?)?;
-            Ok(length)
+fn file_url_path(url: &str) -> Result<PathBuf, FetchError> {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| FetchError::InvalidFileUrl(url.to_string()))
+}
+
+fn read_to_string(url: &str) -> Result<String, FetchError> {
+    if url.starts_with("file:") {
+        return Ok(fs::read_to_string(file_url_path(url)?)?);
+    }
+    Ok(reqwest::get(url)?.error_for_status()?.text()?)
+}
+
+/// The total size of the files in `path`.
+fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+/// The SHA-256 of the file at `path`, in hex.
+fn sha256(path: &Path) -> Result<String, FetchError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 16 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
         }
-        None => Err(format_err!("response doesn't include the content length")),
+        hasher.input(&buf[..read]);
     }
+    Ok(hasher
+        .result()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
 }
 
-fn get_project_dirs() -> Result<ProjectDirs, Error> {
-    info!("Getting project dir");
-    match ProjectDirs::from("", "", APP_NAME) {
-        Some(dirs) => Ok(dirs),
-        None => Err(format_err!("Failed to retrieve project dirs")),
+/// Downloads `url` to `path`, or copies it there if it's a `file://` URL.
+fn download(url: &str, path: &Path, progress: &dyn FetchProgress) -> Result<(), FetchError> {
+    if url.starts_with("file:") {
+        let source = file_url_path(url)?;
+        info!("Copying {} to {}", source.display(), path.display());
+        progress.report(&FetchEvent::DownloadStarted {
+            url,
+            total_bytes: fs::metadata(&source)?.len(),
+        });
+        let bytes = fs::copy(&source, path)?;
+        progress.report(&FetchEvent::Downloaded { bytes });
+        progress.report(&FetchEvent::DownloadFinished);
+        return Ok(());
     }
+
+    let total = get_size(url)?;
+    info!("Total size of download: {}", total);
+
+    info!("Creating file for download: {}", path.display());
+    let file = File::create(path)?;
+
+    progress.report(&FetchEvent::DownloadStarted {
+        url,
+        total_bytes: total,
+    });
+    let mut dest = DownloadProgress::new(file, |bytes| {
+        progress.report(&FetchEvent::Downloaded { bytes });
+    });
+
+    let mut resp = reqwest::get(url)?.error_for_status()?;
+    io::copy(&mut resp, &mut dest)?;
+
+    progress.report(&FetchEvent::DownloadFinished);
+    Ok(())
+}
+
+fn get_size<U: AsRef<str>>(url: U) -> Result<u64, FetchError> {
+    let client = reqwest::Client::new();
+    let response = client.head(url.as_ref()).send()?.error_for_status()?;
+    response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|len| len.to_str().ok())
+        .and_then(|len| u64::from_str(len).ok())
+        .ok_or_else(|| FetchError::MissingContentLength(url.as_ref().to_string()))
 }
 
-fn dl_url<R>(revision: R) -> Result<String, Error>
+fn get_project_dirs() -> Result<ProjectDirs, FetchError> {
+    info!("Getting project dir");
+    ProjectDirs::from("", "", APP_NAME).ok_or(FetchError::NoDataDir)
+}
+
+fn dl_url<R>(host: &str, revision: R) -> Result<String, FetchError>
 where
     R: AsRef<str>,
 {
-    #[cfg(target_os = "linux")]
-    {
-        Ok(format!(
-            "{}/chromium-browser-snapshots/Linux_x64/{}/{}.zip",
-            DEFAULT_HOST,
-            revision.as_ref(),
-            archive_name(revision.as_ref())?
-        ))
-    }
-
-    #[cfg(target_os = "macos")]
-    {
-        Ok(format!(
-            "{}/chromium-browser-snapshots/Mac/{}/{}.zip",
-            DEFAULT_HOST,
-            revision.as_ref(),
-            archive_name(revision.as_ref())?
-        ))
-    }
+    Ok(format!(
+        "{}/{}",
+        host.trim_end_matches('/'),
+        archive_path(revision)?
+    ))
+}
 
-    #[cfg(windows)]
-    {
-        Ok(format!(
-            "{}/chromium-browser-snapshots/Win_x64/{}/{}.zip",
-            DEFAULT_HOST,
-            revision.as_ref(),
-            archive_name(revision.as_ref())?
-        ))
-    }
+/// Where the archive of `revision` is on the download host.
+fn archive_path<R: AsRef<str>>(revision: R) -> Result<String, FetchError> {
+    Ok(format!(
+        "chromium-browser-snapshots/{}/{}/{}.zip",
+        SNAPSHOTS_PLATFORM,
+        revision.as_ref(),
+        archive_name(revision.as_ref())?
+    ))
 }
 
-fn archive_name<R: AsRef<str>>(_revision: R) -> Result<&'static str, Error> {
+fn archive_name<R: AsRef<str>>(_revision: R) -> Result<&'static str, FetchError> {
     #[cfg(target_os = "linux")]
     {
         Ok("chrome-linux")
@@ -309,7 +788,11 @@ fn archive_name<R: AsRef<str>>(_revision: R) -> Result<&'static str, Error> {
     #[cfg(windows)]
     {
         // Windows archive name changed at r591479.
-        if revision.as_ref().parse::<u32>()? > 591_479 {
+        let revision = _revision.as_ref();
+        let revision_number = revision
+            .parse::<u32>()
+            .map_err(|_| FetchError::InvalidRevision(revision.to_string()))?;
+        if revision_number > 591_479 {
             Ok("chrome-win")
         } else {
             Ok("chrome-win32")
@@ -317,9 +800,294 @@ fn archive_name<R: AsRef<str>>(_revision: R) -> Result<&'static str, Error> {
     }
 }
 
+#[cfg(feature = "progress-bars")]
+fn default_progress() -> Box<dyn FetchProgress> {
+    Box::new(ProgressBars::default())
+}
+
+#[cfg(not(feature = "progress-bars"))]
+fn default_progress() -> Box<dyn FetchProgress> {
+    Box::new(|_: &FetchEvent| {})
+}
+
+#[cfg(feature = "progress-bars")]
 fn in_ci() -> bool {
     match env::var("IN_CI") {
         Ok(s) => s == "true",
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_urls_for_mirrors() {
+        let url = dl_url("http://mirror.internal:8080/", "634997").unwrap();
+        assert!(url.starts_with("http://mirror.internal:8080/chromium-browser-snapshots/"));
+        assert!(url.contains("/634997/chrome-"));
+        assert!(url.ends_with(".zip"));
+    }
+
+    #[test]
+    fn copies_archives_from_file_urls() {
+        let mirror = tempfile::tempdir().unwrap();
+        let archive = mirror.path().join("chrome.zip");
+        fs::write(&archive, b"not really a zip").unwrap();
+        let url = reqwest::Url::from_file_path(&archive).unwrap();
+
+        let download_dir = tempfile::tempdir().unwrap();
+        let downloaded = download_dir.path().join("chrome.zip");
+        let events = std::sync::Mutex::new(vec![]);
+        let record = |event: &FetchEvent| events.lock().unwrap().push(format!("{:?}", event));
+        download(url.as_str(), &downloaded, &record).unwrap();
+        assert_eq!(b"not really a zip".to_vec(), fs::read(&downloaded).unwrap());
+
+        assert_eq!(
+            vec![
+                format!(
+                    "DownloadStarted {{ url: {:?}, total_bytes: 16 }}",
+                    url.as_str()
+                ),
+                "Downloaded { bytes: 16 }".to_string(),
+                "DownloadFinished".to_string(),
+            ],
+            *events.lock().unwrap()
+        );
+
+        match download("file://not a path", &downloaded, &record) {
+            Err(FetchError::InvalidFileUrl(_)) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    /// A fetcher installing into a temporary directory from a mirror in another, which has an
+    /// archive holding just an empty executable.
+    fn fetcher_with_mirror(
+        rev: &str,
+    ) -> (Fetcher<'_>, tempfile::TempDir, tempfile::TempDir, PathBuf) {
+        let data_dir = tempfile::tempdir().unwrap();
+        let mirror = tempfile::tempdir().unwrap();
+        let fetcher = Fetcher::with_cache(rev, ChromeCache::in_dir(data_dir.path()).unwrap())
+            .with_host(
+                reqwest::Url::from_directory_path(mirror.path())
+                    .unwrap()
+                    .as_str(),
+            );
+
+        let archive = mirror.path().join(archive_path(rev).unwrap());
+        fs::create_dir_all(archive.parent().unwrap()).unwrap();
+        let executable = fetcher.cache.chrome_path(rev).unwrap();
+        let executable = executable
+            .strip_prefix(fetcher.cache.base_path(rev))
+            .unwrap();
+        let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+        zip.start_file(
+            executable.to_str().unwrap(),
+            zip::write::FileOptions::default().unix_permissions(0o755),
+        )
+        .unwrap();
+        zip.finish().unwrap();
+
+        (fetcher, data_dir, mirror, archive)
+    }
+
+    /// The names of the files in `dir`, but for lock files.
+    fn files_in(dir: &Path) -> Vec<String> {
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| !name.ends_with(".lock"))
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Installs an executable containing `contents` as `rev`.
+    fn fake_install(cache: &ChromeCache, rev: &str, contents: &[u8]) {
+        let executable = cache.chrome_path(rev).unwrap();
+        fs::create_dir_all(executable.parent().unwrap()).unwrap();
+        fs::write(&executable, contents).unwrap();
+        fs::write(cache.base_path(rev).join(INSTALLED_MARKER), "").unwrap();
+    }
+
+    #[test]
+    fn installs_archives_listed_in_manifests() {
+        let (fetcher, data_dir, mirror, archive) = fetcher_with_mirror("1234");
+        let manifest = mirror.path().join("SHA256SUMS");
+        fs::write(
+            &manifest,
+            format!(
+                "{}  ./some/other.zip\n{} *./{}\n",
+                "0".repeat(64),
+                sha256(&archive).unwrap(),
+                archive_path("1234").unwrap()
+            ),
+        )
+        .unwrap();
+        let fetcher =
+            fetcher.with_manifest(reqwest::Url::from_file_path(&manifest).unwrap().as_str());
+
+        let path = fetcher.run().unwrap();
+        assert!(path.is_file());
+        assert_eq!(Some(path), fetcher.cache.executable("1234"));
+        // nothing left over but the install itself
+        assert_eq!(
+            vec![format!("{}-1234", PLATFORM)],
+            files_in(data_dir.path())
+        );
+
+        // an installed revision isn't checked against the manifest again
+        fs::write(&manifest, "").unwrap();
+        fetcher.fetch().unwrap();
+    }
+
+    #[test]
+    fn rejects_archives_with_wrong_checksums() {
+        let (fetcher, data_dir, _mirror, archive) = fetcher_with_mirror("1234");
+        let fetcher = fetcher.with_sha256(&"0".repeat(64));
+
+        match fetcher.fetch() {
+            Err(FetchError::ChecksumMismatch { expected, actual }) => {
+                assert_eq!("0".repeat(64), expected);
+                assert_eq!(sha256(&archive).unwrap(), actual);
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+        assert!(files_in(data_dir.path()).is_empty());
+    }
+
+    #[test]
+    fn repairs_incomplete_installs() {
+        let (fetcher, _data_dir, _mirror, archive) = fetcher_with_mirror("1234");
+        let executable = fetcher.cache.chrome_path("1234").unwrap();
+        fs::create_dir_all(executable.parent().unwrap()).unwrap();
+        fs::write(executable.with_file_name("half a library"), b"").unwrap();
+        assert!(fetcher.cache.revisions().unwrap().is_empty());
+
+        let fetcher = fetcher.with_sha256(&sha256(&archive).unwrap().to_uppercase());
+        assert_eq!(executable, fetcher.run().unwrap());
+        assert!(fs::read(&executable).unwrap().is_empty());
+        assert_eq!(
+            sha256(&archive).unwrap(),
+            fs::read_to_string(fetcher.cache.base_path("1234").join(INSTALLED_MARKER)).unwrap()
+        );
+    }
+
+    #[test]
+    fn keeps_installs_from_before_markers() {
+        let (fetcher, _data_dir, _mirror, _archive) = fetcher_with_mirror("1234");
+        fake_install(&fetcher.cache, "1234", b"chrome");
+        let marker = fetcher.cache.base_path("1234").join(INSTALLED_MARKER);
+        fs::remove_file(&marker).unwrap();
+
+        let fetcher = fetcher.with_sha256(&"0".repeat(64));
+        assert_eq!(
+            vec!["1234"],
+            fetcher
+                .cache
+                .revisions()
+                .unwrap()
+                .iter()
+                .map(|revision| revision.revision.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("", fs::read_to_string(&marker).unwrap());
+        // rather than downloaded again, and failing the checksum
+        let executable = fetcher.fetch().unwrap();
+        assert_eq!(b"chrome".to_vec(), fs::read(executable).unwrap());
+    }
+
+    #[test]
+    fn lists_and_removes_revisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ChromeCache::in_dir(dir.path()).unwrap();
+        fake_install(&cache, "100", b"chrome");
+        fake_install(&cache, "200", b"chromium");
+        fake_install(&cache, "300", b"");
+        let incomplete = cache.chrome_path("400").unwrap();
+        fs::create_dir_all(incomplete.parent().unwrap()).unwrap();
+
+        let revisions = cache.revisions().unwrap();
+        assert_eq!(
+            vec![("100", 6), ("200", 8), ("300", 0)],
+            revisions
+                .iter()
+                .map(|revision| (revision.revision.as_str(), revision.size))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(cache.base_path("100"), revisions[0].path);
+
+        assert!(cache.remove("200").unwrap());
+        assert!(!cache.remove("200").unwrap());
+        assert_eq!(None, cache.executable("200"));
+
+        assert_eq!(
+            vec!["100".to_string(), "400".to_string()],
+            cache.remove_all_except("300").unwrap()
+        );
+        assert_eq!(vec![format!("{}-300", PLATFORM)], files_in(dir.path()));
+    }
+
+    #[test]
+    fn fails_downloads_with_error_statuses() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/missing.zip", server.server_addr());
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let response = tiny_http::Response::from_string("<html>Not Found</html>")
+                    .with_status_code(404);
+                request.respond(response).unwrap();
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("archive.zip");
+        match download(&url, &path, &|_: &FetchEvent| {}) {
+            Err(FetchError::Download(error)) => {
+                assert_eq!(Some(reqwest::StatusCode::NOT_FOUND), error.status())
+            }
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn lists_revisions_in_numeric_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ChromeCache::in_dir(dir.path()).unwrap();
+        for rev in &["634997", "99999", "1000000", "700000"] {
+            fake_install(&cache, rev, b"");
+        }
+
+        assert_eq!(
+            vec!["99999", "634997", "700000", "1000000"],
+            cache
+                .revisions()
+                .unwrap()
+                .iter()
+                .map(|revision| revision.revision.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn waits_for_other_installs_of_the_same_revision() {
+        // the mirror has nothing, so fetching only works if the other install happens first
+        let (fetcher, _data_dir, _mirror, archive) = fetcher_with_mirror("1234");
+        fs::remove_file(&archive).unwrap();
+        let cache = fetcher.cache.clone();
+        let lock = cache.lock("1234").unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || tx.send(fetcher.fetch()).unwrap());
+        assert!(rx
+            .recv_timeout(std::time::Duration::from_millis(200))
+            .is_err());
+
+        fake_install(&cache, "1234", b"");
+        drop(lock);
+        let path = rx.recv().unwrap().unwrap();
+        assert_eq!(cache.executable("1234"), Some(path));
+    }
+}
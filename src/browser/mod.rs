@@ -2,28 +2,45 @@ use std::sync::mpsc;
 use std::sync::Arc;
 use std::sync::Mutex;
 
-use failure::Error;
+use failure::format_err;
 use log::*;
-use which::which;
 
 use serde;
 
 use crate::protocol::browser::methods::GetVersion;
 pub use crate::protocol::browser::methods::VersionInformationReturnObject;
-use crate::protocol::target::methods::{CreateTarget, SetDiscoverTargets};
-use crate::protocol::{self, Event};
-use crate::util;
+use crate::protocol::target::methods::{
+    CreateBrowserContext, CreateTarget, DisposeBrowserContext, SetDiscoverTargets,
+};
+use crate::protocol::{self, Event, Message};
+use crate::{util, Error};
 
-pub use process::LaunchOptionsBuilder;
-use process::{LaunchOptions, Process};
+pub use executable::{
+    chrome_version, default_executable, ChromeVersion, CHROME_ENV, MIN_CHROME_VERSION,
+};
+#[cfg(feature = "progress-bars")]
+pub use fetcher::ProgressBars;
+pub use fetcher::{
+    CachedRevision, ChromeCache, FetchError, FetchEvent, FetchProgress, Fetcher, CUR_REV,
+    DOWNLOAD_HOST_ENV,
+};
+pub use pool::{BrowserPool, Isolation, LeasedTab, PoolConfig, PoolExhausted};
+pub use process::{LaunchError, LaunchOptionsBuilder};
+use process::{LaunchOptions, Process, DEFAULT_IDLE_BROWSER_TIMEOUT};
 use std::time::Duration;
+pub use supervisor::{Restart, RestartPolicy, SupervisedBrowser};
 pub use tab::Tab;
-use transport::Transport;
+use transport::{
+    Connection, DisconnectReason, MethodDestination, SessionRecorder, Transport, TransportHook,
+};
 
+mod executable;
 mod fetcher;
+mod pool;
 mod process;
+mod supervisor;
 pub mod tab;
-mod transport;
+pub mod transport;
 
 /// A handle to an instance of Chrome / Chromium, which wraps a WebSocket connection to its debugging port.
 ///
@@ -52,7 +69,7 @@ mod transport;
 /// ["Browser" domain](https://chromedevtools.github.io/devtools-protocol/tot/Browser)
 /// (such as for resizing the window in non-headless mode), we currently don't implement those.
 pub struct Browser {
-    _process: Process,
+    _process: Option<Process>,
     transport: Arc<Transport>,
     tabs: Arc<Mutex<Vec<Arc<Tab>>>>,
 }
@@ -60,15 +77,73 @@ pub struct Browser {
 impl Browser {
     /// Launch a new Chrome browser.
     ///
-    /// The browser will have its user data (aka "profile") directory stored in a temporary directory.
+    /// Unless `LaunchOptions::user_data_dir` is set, the browser will have its user data (aka
+    /// "profile") directory stored in a temporary directory.
     /// The browser process will be killed when this struct is dropped.
     pub fn new(launch_options: LaunchOptions) -> Result<Self, Error> {
-        let process = Process::new(launch_options)?;
+        let idle_browser_timeout = launch_options.idle_browser_timeout;
+        let recorder = launch_options
+            .record_session_to
+            .as_ref()
+            .map(SessionRecorder::create)
+            .transpose()?
+            .map(Arc::new);
+        let mut process = Process::new(launch_options)?;
 
-        let transport = Arc::new(Transport::new(process.debug_ws_url.clone())?);
+        let transport = Arc::new(match process.take_pipe() {
+            Some((to_browser, from_browser)) => {
+                Transport::over_pipe(to_browser, from_browser, idle_browser_timeout, recorder)?
+            }
+            None => Transport::new(
+                process
+                    .debug_ws_url
+                    .clone()
+                    .ok_or_else(|| format_err!("Chrome didn't give us a WebSocket URL"))?,
+                idle_browser_timeout,
+                recorder,
+            )?,
+        });
+
+        transport.set_exit_probe(process.exit_probe());
 
         trace!("created transport");
 
+        Self::create(Some(process), transport, idle_browser_timeout)
+    }
+
+    /// Drive a browser over a connection of your own making rather than launching one, e.g. a
+    /// [MockBrowser](transport/struct.MockBrowser.html) in unit tests.
+    ///
+    /// `connect` is given the channel over which the connection should pass on the messages it
+    /// receives.
+    pub fn with_connection<C, F>(connect: F) -> Result<Self, Error>
+    where
+        C: Connection + 'static,
+        F: FnOnce(mpsc::Sender<Message>) -> Result<C, Error>,
+    {
+        Self::with_connection_and_idle_timeout(connect, DEFAULT_IDLE_BROWSER_TIMEOUT)
+    }
+
+    pub(crate) fn with_connection_and_idle_timeout<C, F>(
+        connect: F,
+        idle_browser_timeout: Duration,
+    ) -> Result<Self, Error>
+    where
+        C: Connection + 'static,
+        F: FnOnce(mpsc::Sender<Message>) -> Result<C, Error>,
+    {
+        let transport = Arc::new(Transport::with_connection(
+            |messages_tx| connect(messages_tx).map_err(failure::Error::from),
+            idle_browser_timeout,
+        )?);
+        Self::create(None, transport, idle_browser_timeout)
+    }
+
+    fn create(
+        process: Option<Process>,
+        transport: Arc<Transport>,
+        idle_browser_timeout: Duration,
+    ) -> Result<Self, Error> {
         let tabs = Arc::new(Mutex::new(vec![]));
 
         let browser = Self {
@@ -78,7 +153,7 @@ impl Browser {
         };
 
         let incoming_events_rx = browser.transport.listen_to_browser_events();
-        browser.handle_browser_level_events(incoming_events_rx);
+        browser.handle_browser_level_events(incoming_events_rx, idle_browser_timeout);
         trace!("created browser event listener");
 
         // so we get events like 'targetCreated' and 'targetDestroyed'
@@ -122,14 +197,41 @@ impl Browser {
     /// # }
     /// ```
     ///
-    /// Currently does not support creating the tab in a new "browser context", aka an incognito
-    /// window.
+    /// To open it in a new "browser context", aka an incognito window, see `new_tab_in_context`.
     pub fn new_tab(&self) -> Result<Arc<Tab>, Error> {
+        self.create_tab(None)
+    }
+
+    /// Create a new browser context, aka an incognito window: tabs opened in it with
+    /// `new_tab_in_context` share cookies, storage and cache with each other, but not with tabs
+    /// in any other context.
+    ///
+    /// Returns the ID of the new context.
+    pub fn new_context(&self) -> Result<String, Error> {
+        Ok(self
+            .call_method(CreateBrowserContext {})?
+            .browser_context_id)
+    }
+
+    /// Create a new tab in the given browser context, and return a handle to it.
+    pub fn new_tab_in_context(&self, context_id: &str) -> Result<Arc<Tab>, Error> {
+        self.create_tab(Some(context_id))
+    }
+
+    /// Dispose of a browser context created with `new_context`, closing all its tabs.
+    pub fn dispose_context(&self, context_id: &str) -> Result<(), Error> {
+        self.call_method(DisposeBrowserContext {
+            browser_context_id: context_id,
+        })?;
+        Ok(())
+    }
+
+    fn create_tab(&self, browser_context_id: Option<&str>) -> Result<Arc<Tab>, Error> {
         let create_target = CreateTarget {
             url: "about:blank",
             width: None,
             height: None,
-            browser_context_id: None,
+            browser_context_id,
             enable_begin_frame_control: None,
         };
 
@@ -163,14 +265,18 @@ impl Browser {
         self.call_method(GetVersion {})
     }
 
-    fn handle_browser_level_events(&self, events_rx: mpsc::Receiver<Event>) {
+    fn handle_browser_level_events(
+        &self,
+        events_rx: mpsc::Receiver<Event>,
+        idle_browser_timeout: Duration,
+    ) {
         let tabs = Arc::clone(&self.tabs);
         let transport = Arc::clone(&self.transport);
 
         std::thread::spawn(move || {
             trace!("Starting browser's event handling loop");
             loop {
-                match events_rx.recv_timeout(Duration::from_millis(20_000)) {
+                match events_rx.recv_timeout(idle_browser_timeout) {
                     Err(_) => {
                         break;
                     }
@@ -204,7 +310,11 @@ impl Browser {
                                 }
                             }
                             Event::TargetDestroyed(ev) => {
-                                trace!("Target destroyed: {:?}", ev.params.target_id);
+                                let target_id = ev.params.target_id;
+                                trace!("Target destroyed: {:?}", target_id);
+                                tabs.lock()
+                                    .unwrap()
+                                    .retain(|tab| *tab.get_target_id() != target_id);
                             }
                             _ => {}
                         }
@@ -215,53 +325,62 @@ impl Browser {
         });
     }
 
-    /// Call a browser method.
+    /// How long protocol calls on the browser, and on tabs which haven't been given a
+    /// timeout of their own, wait for a response before failing with a `MethodTimeout` error.
+    /// Defaults to 10 seconds.
+    pub fn set_default_timeout(&self, timeout: Duration) -> &Self {
+        self.transport.set_call_timeout(timeout);
+        self
+    }
+
+    /// Call `callback` once the connection to the browser ends, e.g. because it crashed,
+    /// with the reason why. Called right away if that's already happened.
     ///
-    /// See the `cdtp` module documentation for available methods.
-    fn call_method<C>(&self, method: C) -> Result<C::ReturnObject, Error>
+    /// After that, calls on the browser and its tabs fail with a `ConnectionClosed` error
+    /// giving the same reason.
+    pub fn on_disconnect<F>(&self, callback: F)
     where
-        C: protocol::Method + serde::Serialize,
+        F: Fn(&DisconnectReason) + Send + 'static,
     {
-        self.transport.call_method_on_browser(method)
+        self.transport.on_disconnect(callback);
     }
 
-    #[allow(dead_code)]
-    #[cfg(test)]
-    pub(crate) fn process(&self) -> &Process {
-        #[allow(clippy::used_underscore_binding)]
-        &self._process
-    }
-}
-
-pub fn default_executable() -> Result<std::path::PathBuf, String> {
-    // TODO Look at $BROWSER and if it points to a chrome binary
-    // $BROWSER may also provide default arguments, which we may
-    // or may not override later on.
-
-    for app in &["google-chrome-stable", "chromium"] {
-        if let Ok(path) = which(app) {
-            return Ok(path);
-        }
+    /// Have `hook` observe the protocol traffic to and from this browser and its tabs from now
+    /// on, e.g. a [ProtocolLogger](transport/struct.ProtocolLogger.html) to log it in full.
+    pub fn add_transport_hook(&self, hook: Arc<dyn TransportHook>) {
+        self.transport.add_hook(hook);
     }
 
-    #[cfg(target_os = "macos")]
+    /// Make a protocol call on the browser, waiting as long as `set_default_timeout` says for a
+    /// response.
+    ///
+    /// See the `protocol` module for available methods.
+    pub fn call_method<C>(&self, method: C) -> Result<C::ReturnObject, Error>
+    where
+        C: protocol::Method + serde::Serialize,
     {
-        let default_paths = &["/Applications/Google Chrome.app/Contents/MacOS/Google Chrome"][..];
-        for path in default_paths {
-            if std::path::Path::new(path).exists() {
-                return Ok(path.into());
-            }
-        }
+        Ok(self.transport.call_method_on_browser(method)?)
     }
 
-    #[cfg(windows)]
+    /// Make a protocol call on the browser which fails with a `MethodTimeout` error unless
+    /// answered within `timeout`, rather than the default one.
+    pub fn call_method_with_timeout<C>(
+        &self,
+        method: C,
+        timeout: Duration,
+    ) -> Result<C::ReturnObject, Error>
+    where
+        C: protocol::Method + serde::Serialize,
     {
-        if let Some(path) = get_chrome_path_from_registry() {
-            if path.exists() {
-                return Ok(path);
-            }
-        }
+        Ok(self
+            .transport
+            .call_method_with_timeout(method, MethodDestination::Browser, timeout)?)
     }
 
-    Err("Could not auto detect a chrome executable".to_string())
+    #[allow(dead_code)]
+    #[cfg(test)]
+    pub(crate) fn process(&self) -> Option<&Process> {
+        #[allow(clippy::used_underscore_binding)]
+        self._process.as_ref()
+    }
 }
@@ -0,0 +1,330 @@
+use std::ops::Deref;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use failure::Fail;
+use log::*;
+
+use super::process::LaunchOptions;
+use super::{Browser, Tab};
+use crate::Error;
+
+/// How the tabs handed out by a `BrowserPool` are kept apart from each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Isolation {
+    /// Each tab gets a browser of its own.
+    Browsers,
+    /// All tabs share one browser, but each gets a browser context (aka incognito window) of its
+    /// own, which is much cheaper but means a crash takes all of them down.
+    Contexts,
+}
+
+/// How a `BrowserPool` is set up.
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    /// How many tabs can be leased at once. Defaults to 4.
+    pub size: usize,
+    /// Defaults to `Isolation::Browsers`.
+    pub isolation: Isolation,
+    /// How many times a browser (or context) can be leased before it's replaced with a fresh
+    /// one, or `None` to keep it for as long as it's healthy. Defaults to `None`.
+    pub max_uses: Option<usize>,
+    /// How long `lease` waits for a tab to become available. Defaults to 30 seconds.
+    pub lease_timeout: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            size: 4,
+            isolation: Isolation::Browsers,
+            max_uses: None,
+            lease_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "No tab became available within {:?}", timeout)]
+pub struct PoolExhausted {
+    pub timeout: Duration,
+}
+
+type Launcher = Box<dyn Fn() -> Result<Browser, Error> + Send + Sync>;
+
+/// Keeps a number of browsers (or browser contexts in a single browser) around for running
+/// jobs in parallel, handing out a tab in one of them to each job in turn.
+///
+/// Each browser is health-checked before its tab is handed out, and replaced if it doesn't
+/// respond. Once a job is done with its `LeasedTab`, the tab is navigated to `about:blank` and
+/// has its cookies cleared, ready for the next job.
+///
+/// ```rust,no_run
+/// # use failure::Error;
+/// # fn main() -> Result<(), Error> {
+/// #
+/// use headless_chrome::LaunchOptionsBuilder;
+/// use headless_chrome::browser::{BrowserPool, PoolConfig};
+///
+/// let pool = BrowserPool::new(
+///     LaunchOptionsBuilder::default().build().unwrap(),
+///     PoolConfig { size: 8, max_uses: Some(100), ..PoolConfig::default() },
+/// )?;
+///
+/// let tab = pool.lease()?;
+/// tab.navigate_to("https://www.wikipedia.org")?;
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct BrowserPool {
+    pool: Arc<Pool>,
+}
+
+struct Pool {
+    launch: Launcher,
+    config: PoolConfig,
+    /// The browser all the contexts are in, with `Isolation::Contexts`.
+    shared_browser: Mutex<Option<Arc<Browser>>>,
+    slots: Mutex<Slots>,
+    slot_released: Condvar,
+}
+
+struct Slots {
+    idle: Vec<Slot>,
+    /// How many slots we failed to replace, and so still need to be created.
+    missing: usize,
+}
+
+/// A browser, or context in the shared browser, and the tab we hand out in it.
+struct Slot {
+    browser: Arc<Browser>,
+    context_id: Option<String>,
+    tab: Arc<Tab>,
+    uses: usize,
+}
+
+impl BrowserPool {
+    /// Launch browsers with the given options to fill the pool.
+    pub fn new(launch_options: LaunchOptions<'static>, config: PoolConfig) -> Result<Self, Error> {
+        Self::with_launcher(move || Browser::new(launch_options.clone()), config)
+    }
+
+    /// Like `new`, but launching browsers by calling `launch`, e.g. to connect to a
+    /// [MockBrowser](transport/struct.MockBrowser.html) in tests.
+    pub fn with_launcher<F>(launch: F, config: PoolConfig) -> Result<Self, Error>
+    where
+        F: Fn() -> Result<Browser, Error> + Send + Sync + 'static,
+    {
+        let pool = Arc::new(Pool {
+            launch: Box::new(launch),
+            shared_browser: Mutex::new(None),
+            slots: Mutex::new(Slots {
+                idle: vec![],
+                missing: 0,
+            }),
+            slot_released: Condvar::new(),
+            config,
+        });
+        for _ in 0..pool.config.size {
+            let slot = pool.new_slot()?;
+            pool.slots.lock().unwrap().idle.push(slot);
+        }
+        Ok(Self { pool })
+    }
+
+    /// Lease a tab, waiting up to `PoolConfig::lease_timeout` for one to become available.
+    ///
+    /// The tab goes back into the pool when the returned `LeasedTab` is dropped.
+    pub fn lease(&self) -> Result<LeasedTab, Error> {
+        let timeout = self.pool.config.lease_timeout;
+        let deadline = Instant::now() + timeout;
+        let mut slots = self.pool.slots.lock().unwrap();
+        let slot = loop {
+            if let Some(slot) = slots.idle.pop() {
+                break Some(slot);
+            }
+            if slots.missing > 0 {
+                slots.missing -= 1;
+                break None;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(PoolExhausted { timeout }.into());
+            }
+            slots = self
+                .pool
+                .slot_released
+                .wait_timeout(slots, deadline - now)
+                .unwrap()
+                .0;
+        };
+        drop(slots);
+
+        let slot = match slot {
+            Some(slot) if slot.is_healthy() => slot,
+            Some(slot) => {
+                warn!("Replacing unhealthy browser in pool");
+                self.pool.discard(slot);
+                self.pool.replacement_slot()?
+            }
+            None => self.pool.replacement_slot()?,
+        };
+        Ok(LeasedTab {
+            pool: Arc::clone(&self.pool),
+            slot: Some(slot),
+        })
+    }
+
+    /// How many tabs are waiting to be leased right now.
+    pub fn idle_count(&self) -> usize {
+        self.pool.slots.lock().unwrap().idle.len()
+    }
+}
+
+impl Pool {
+    fn new_slot(&self) -> Result<Slot, Error> {
+        match self.config.isolation {
+            Isolation::Browsers => {
+                let browser = Arc::new((self.launch)()?);
+                let tab = browser.wait_for_initial_tab()?;
+                Ok(Slot {
+                    browser,
+                    context_id: None,
+                    tab,
+                    uses: 0,
+                })
+            }
+            Isolation::Contexts => {
+                let browser = self.shared_browser()?;
+                let context_id = browser.new_context()?;
+                let tab = browser.new_tab_in_context(&context_id)?;
+                Ok(Slot {
+                    browser,
+                    context_id: Some(context_id),
+                    tab,
+                    uses: 0,
+                })
+            }
+        }
+    }
+
+    /// A new slot to make up for one we've discarded, or an error after taking note that it's
+    /// still missing, so that the next lease tries again.
+    fn replacement_slot(&self) -> Result<Slot, Error> {
+        self.new_slot().map_err(|error| {
+            warn!("Failed to replace browser in pool: {}", error);
+            self.slots.lock().unwrap().missing += 1;
+            self.slot_released.notify_one();
+            error
+        })
+    }
+
+    /// The browser the contexts are in, relaunched if it has stopped responding.
+    fn shared_browser(&self) -> Result<Arc<Browser>, Error> {
+        let mut shared_browser = self.shared_browser.lock().unwrap();
+        if let Some(browser) = shared_browser.as_ref() {
+            if browser.get_version().is_ok() {
+                return Ok(Arc::clone(browser));
+            }
+            warn!("Relaunching the pool's shared browser");
+        }
+        let browser = Arc::new((self.launch)()?);
+        *shared_browser = Some(Arc::clone(&browser));
+        Ok(browser)
+    }
+
+    fn discard(&self, slot: Slot) {
+        if let Some(context_id) = slot.context_id {
+            if let Err(error) = slot.browser.dispose_context(&context_id) {
+                debug!("Failed to dispose of context {}: {}", context_id, error);
+            }
+        }
+    }
+
+    fn release(&self, slot: Slot) {
+        self.slots.lock().unwrap().idle.push(slot);
+        self.slot_released.notify_one();
+    }
+}
+
+impl Slot {
+    fn is_healthy(&self) -> bool {
+        !self.tab.is_crashed() && self.browser.get_version().is_ok()
+    }
+
+    /// Gets the tab ready for its next lease.
+    fn reset(&self) -> Result<(), Error> {
+        self.tab.navigate_to("about:blank")?;
+        self.tab.clear_browser_cookies()?;
+        Ok(())
+    }
+}
+
+/// A tab leased from a `BrowserPool`, which derefs to the `Tab` itself.
+///
+/// Dropping it resets the tab and hands it back to the pool, replacing its browser (or context)
+/// with a fresh one if that fails or `PoolConfig::max_uses` has been reached, which can take a
+/// moment.
+pub struct LeasedTab {
+    pool: Arc<Pool>,
+    slot: Option<Slot>,
+}
+
+impl LeasedTab {
+    /// The browser the tab is in.
+    pub fn browser(&self) -> &Arc<Browser> {
+        &self.slot().browser
+    }
+
+    /// The browser context the tab is in, with `Isolation::Contexts`.
+    pub fn context_id(&self) -> Option<&str> {
+        self.slot().context_id.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The tab itself, e.g. to hand to another thread.
+    pub fn tab(&self) -> &Arc<Tab> {
+        &self.slot().tab
+    }
+
+    fn slot(&self) -> &Slot {
+        self.slot.as_ref().unwrap()
+    }
+}
+
+impl Deref for LeasedTab {
+    type Target = Tab;
+
+    fn deref(&self) -> &Tab {
+        &self.slot().tab
+    }
+}
+
+impl Drop for LeasedTab {
+    fn drop(&mut self) {
+        let mut slot = self.slot.take().unwrap();
+        slot.uses += 1;
+
+        let worn_out = match self.pool.config.max_uses {
+            Some(max_uses) => slot.uses >= max_uses,
+            None => false,
+        };
+        let reset = if worn_out { Ok(()) } else { slot.reset() };
+        if !worn_out && reset.is_ok() {
+            self.pool.release(slot);
+            return;
+        }
+
+        match reset {
+            Ok(()) => debug!("Replacing browser in pool after {} uses", slot.uses),
+            Err(error) => warn!(
+                "Replacing browser in pool, as resetting it failed: {}",
+                error
+            ),
+        }
+        self.pool.discard(slot);
+        if let Ok(slot) = self.pool.replacement_slot() {
+            self.pool.release(slot);
+        }
+    }
+}
@@ -6,44 +6,70 @@ use regex::Regex;
 
 use std::{
     borrow::BorrowMut,
+    collections::HashMap,
     ffi::OsStr,
+    fs::File,
     io::{prelude::*, BufRead, BufReader},
     net,
-    process::{Child, Command, Stdio},
+    path::PathBuf,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
 };
 
-#[cfg(windows)]
-use winreg::{enums::HKEY_LOCAL_MACHINE, RegKey};
-
-use super::fetcher::{self, Fetcher};
+use super::fetcher::{self, ChromeCache, Fetcher};
 use crate::util;
 use std::time::Duration;
+use tempfile::TempDir;
+
+/// Arguments we pass to Chrome unless `LaunchOptions::disable_default_args` is set.
+const DEFAULT_ARGS: [&str; 2] = ["--verbose", "--no-first-run"];
+
+/// Default for `LaunchOptions::idle_browser_timeout`.
+pub(crate) const DEFAULT_IDLE_BROWSER_TIMEOUT: Duration = Duration::from_secs(20);
 
 pub struct Process {
-    _child_process: TemporaryProcess,
-    pub debug_ws_url: String,
+    child_process: Arc<Mutex<TemporaryProcess>>,
+    /// The URL to connect to the browser's debugging WebSocket, unless it was launched with
+    /// `remote_debugging_pipe`.
+    pub debug_ws_url: Option<String>,
+    pipe: Option<(File, File)>,
 }
 
+/// Why we couldn't launch a browser.
 #[derive(Debug, Fail)]
-enum ChromeLaunchError {
+pub enum LaunchError {
     #[fail(display = "Chrome launched, but didn't give us a WebSocket URL before we timed out")]
     PortOpenTimeout,
     #[fail(display = "There are no available ports between 8000 and 9000 for debugging")]
     NoAvailablePorts,
     #[fail(display = "The chosen debugging port is already in use")]
     DebugPortInUse,
+    #[fail(display = "Launching with a debugging pipe is only supported on Unix")]
+    #[allow(dead_code)]
+    PipeUnsupported,
+    #[fail(display = "Could not auto detect a Chrome executable")]
+    NoExecutableFound,
+    #[fail(display = "{} is set to {}, which isn't an executable", var, value)]
+    InvalidExecutableEnv { var: &'static str, value: String },
+    #[fail(
+        display = "{} is Chrome {}, but we need at least version {}",
+        path, version, required
+    )]
+    UnsupportedVersion {
+        path: String,
+        version: String,
+        required: u32,
+    },
+    #[fail(
+        display = "Couldn't tell which version of Chrome {} is from: {}",
+        path, output
+    )]
+    UnrecognisedVersion { path: String, output: String },
 }
 
-#[cfg(windows)]
-fn get_chrome_path_from_registry() -> Option<std::path::PathBuf> {
-    RegKey::predef(HKEY_LOCAL_MACHINE)
-        .open_subkey("SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\App Paths\\chrome.exe")
-        .and_then(|key| key.get_value::<String, _>(""))
-        .map(std::path::PathBuf::from)
-        .ok()
-}
-
-struct TemporaryProcess(Child);
+/// A running Chrome process, along with the temporary profile directory it was given (if any),
+/// which is removed once the process has been killed.
+struct TemporaryProcess(Child, #[allow(dead_code)] Option<TempDir>);
 
 impl Drop for TemporaryProcess {
     fn drop(&mut self) {
@@ -54,7 +80,7 @@ impl Drop for TemporaryProcess {
 
 /// Represents the way in which Chrome is run. By default it will search for a Chrome
 /// binary on the system, use an available port for debugging, and start in headless mode.
-#[derive(Builder)]
+#[derive(Builder, Clone)]
 pub struct LaunchOptions<'a> {
     /// Determintes whether to run headless version of the browser. Defaults to true.
     #[builder(default = "true")]
@@ -85,6 +111,93 @@ pub struct LaunchOptions<'a> {
     /// By default, we'll use a revision guaranteed to work with our API.
     #[builder(default = "self.default_revision()")]
     revision: &'static str,
+
+    /// Launch the browser with the given window size (width, height), e.g. `(1920, 1080)`.
+    ///
+    /// In headless mode, this is also the initial size of the viewport.
+    #[builder(default = "None")]
+    window_size: Option<(u32, u32)>,
+
+    /// Additional command line arguments to pass to the browser, e.g. `--lang=de`.
+    #[builder(default)]
+    args: Vec<&'a OsStr>,
+
+    /// Don't pass the arguments we'd usually launch the browser with (`--verbose` and
+    /// `--no-first-run`), only those needed to control it and the ones set via other options.
+    #[builder(default = "false")]
+    disable_default_args: bool,
+
+    /// Environment variables to set for the browser process, in addition to those inherited
+    /// from ours, e.g. `TZ` or `LANG`.
+    #[builder(default)]
+    process_envs: HashMap<String, String>,
+
+    /// Directory to keep the user data (aka "profile") in.
+    ///
+    /// By default, each browser gets a fresh profile in a temporary directory which is removed
+    /// when the browser is. Pointing this at a persistent directory lets you reuse cookies,
+    /// logins and the like across runs. Note that Chrome refuses to share a profile between
+    /// two running instances.
+    #[builder(default = "None")]
+    user_data_dir: Option<PathBuf>,
+
+    /// Determines whether to run the browser inside Chrome's sandbox. Defaults to true.
+    ///
+    /// Running without the sandbox (`--no-sandbox`) is insecure, but often the only way to run
+    /// Chrome as root or inside containers lacking the necessary kernel features.
+    #[builder(default = "true")]
+    sandbox: bool,
+
+    /// Proxy server for the browser to use, e.g. `socks5://127.0.0.1:1080` or
+    /// `http://proxy.example.com:3128`.
+    #[builder(default = "None")]
+    proxy_server: Option<&'a str>,
+
+    /// Semicolon-separated list of hosts that should bypass the `proxy_server`, e.g.
+    /// `<-loopback>;*.internal.example.com`.
+    #[builder(default = "None")]
+    proxy_bypass_list: Option<&'a str>,
+
+    /// How long the browser may go without sending us any messages (events or method call
+    /// responses) before we consider the connection dead and shut it down.
+    #[builder(default = "DEFAULT_IDLE_BROWSER_TIMEOUT")]
+    pub(crate) idle_browser_timeout: Duration,
+
+    /// Talk to the browser over a pair of pipes (`--remote-debugging-pipe`) instead of a
+    /// WebSocket. Defaults to false.
+    ///
+    /// This avoids having to find a free debugging port and waiting for the browser to announce
+    /// its WebSocket URL, both of which can be flaky on busy CI machines. The `port` option is
+    /// ignored in this mode. Only supported on Unix.
+    #[builder(default = "false")]
+    remote_debugging_pipe: bool,
+
+    /// Host to download Chromium from when no `path` is given, instead of
+    /// `https://storage.googleapis.com` (or the `HEADLESS_CHROME_DOWNLOAD_HOST` environment
+    /// variable), e.g. a mirror like `http://mirror.internal:8080` or `file:///srv/chromium`.
+    ///
+    /// See `Fetcher` for the layout a mirror needs.
+    #[builder(default = "None")]
+    download_host: Option<&'a str>,
+
+    /// SHA-256 (in hex) the downloaded Chromium archive must have. See `Fetcher::with_sha256`.
+    #[builder(default = "None")]
+    download_sha256: Option<&'a str>,
+
+    /// URL of a manifest listing the SHA-256 the downloaded Chromium archive must have. See
+    /// `Fetcher::with_manifest`.
+    #[builder(default = "None")]
+    download_manifest: Option<&'a str>,
+
+    /// Directory to keep downloaded revisions of Chromium in, instead of our XDG data
+    /// directory. See `ChromeCache`.
+    #[builder(default = "None")]
+    cache_dir: Option<PathBuf>,
+
+    /// Record every protocol message exchanged with the browser to this file, as JSON lines
+    /// which a `ReplayConnection` can play back.
+    #[builder(default = "None")]
+    pub(crate) record_session_to: Option<PathBuf>,
 }
 
 impl<'a> LaunchOptionsBuilder<'a> {
@@ -96,10 +209,28 @@ impl<'a> LaunchOptionsBuilder<'a> {
 impl Process {
     pub fn new(mut launch_options: LaunchOptions) -> Result<Self, Error> {
         if launch_options.path.is_none() {
-            let fetch = Fetcher::new(launch_options.revision)?;
+            let mut fetch = match &launch_options.cache_dir {
+                Some(dir) => {
+                    Fetcher::with_cache(launch_options.revision, ChromeCache::in_dir(dir.clone())?)
+                }
+                None => Fetcher::new(launch_options.revision)?,
+            };
+            if let Some(host) = launch_options.download_host {
+                fetch = fetch.with_host(host);
+            }
+            if let Some(sha256) = launch_options.download_sha256 {
+                fetch = fetch.with_sha256(sha256);
+            }
+            if let Some(manifest) = launch_options.download_manifest {
+                fetch = fetch.with_manifest(manifest);
+            }
             launch_options.path = Some(fetch.run()?);
         }
 
+        if launch_options.remote_debugging_pipe {
+            return Self::start_process_with_pipe(&launch_options);
+        }
+
         let mut process = Self::start_process(&launch_options)?;
 
         info!("Started Chrome. PID: {}", process.0.id());
@@ -108,7 +239,7 @@ impl Process {
         let mut attempts = 0;
         loop {
             if attempts > 10 {
-                return Err(ChromeLaunchError::NoAvailablePorts {}.into());
+                return Err(LaunchError::NoAvailablePorts {}.into());
             }
 
             match Self::ws_url_from_output(process.0.borrow_mut()) {
@@ -135,47 +266,160 @@ impl Process {
         }
 
         Ok(Self {
-            _child_process: process,
-            debug_ws_url: url,
+            child_process: Arc::new(Mutex::new(process)),
+            debug_ws_url: Some(url),
+            pipe: None,
         })
     }
 
+    /// Our ends of the pipes to a browser launched with `remote_debugging_pipe`: the one it
+    /// reads method calls from and the one it writes messages to.
+    ///
+    /// Can only be taken once; returns `None` for browsers we talk to via WebSocket.
+    pub fn take_pipe(&mut self) -> Option<(File, File)> {
+        self.pipe.take()
+    }
+
+    /// A way of checking on the browser's exit status which doesn't keep the process alive once
+    /// we've dropped it.
+    pub(crate) fn exit_probe(&self) -> impl Fn() -> Option<ExitStatus> + Send + 'static {
+        let child_process = Arc::downgrade(&self.child_process);
+        move || {
+            let child_process = child_process.upgrade()?;
+            let mut child_process = child_process.lock().unwrap();
+            child_process.0.try_wait().ok().and_then(|status| status)
+        }
+    }
+
     fn start_process(launch_options: &LaunchOptions) -> Result<TemporaryProcess, Error> {
         let debug_port = if let Some(port) = launch_options.port {
             port
         } else {
-            get_available_port().ok_or(ChromeLaunchError::NoAvailablePorts {})?
+            get_available_port().ok_or(LaunchError::NoAvailablePorts {})?
         };
         let port_option = format!("--remote-debugging-port={}", debug_port);
 
+        let (mut command, temporary_user_data_dir) = Self::command(launch_options)?;
+        let process = TemporaryProcess(
+            command.arg(port_option).stderr(Stdio::piped()).spawn()?,
+            temporary_user_data_dir,
+        );
+        Ok(process)
+    }
+
+    #[cfg(unix)]
+    fn start_process_with_pipe(launch_options: &LaunchOptions) -> Result<Self, Error> {
+        use std::os::unix::io::AsRawFd;
+        use std::os::unix::process::CommandExt;
+
+        let (browser_reads, to_browser) = create_pipe()?;
+        let (from_browser, browser_writes) = create_pipe()?;
+        let (read_fd, write_fd) = (browser_reads.as_raw_fd(), browser_writes.as_raw_fd());
+
+        let (mut command, temporary_user_data_dir) = Self::command(launch_options)?;
+        command
+            .arg("--remote-debugging-pipe")
+            // There's nothing to scrape from stderr, and nobody to drain it if we piped it.
+            .stderr(Stdio::null());
+        unsafe {
+            command.pre_exec(move || {
+                // Move both ends out of the way first, in case either of them already is fd 3 or
+                // 4. The copies are closed on exec; dup2 clears that flag on fds 3 and 4.
+                let read_fd = libc::fcntl(read_fd, libc::F_DUPFD_CLOEXEC, 10);
+                let write_fd = libc::fcntl(write_fd, libc::F_DUPFD_CLOEXEC, 10);
+                if read_fd < 0
+                    || write_fd < 0
+                    || libc::dup2(read_fd, 3) < 0
+                    || libc::dup2(write_fd, 4) < 0
+                {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let process = TemporaryProcess(command.spawn()?, temporary_user_data_dir);
+
+        info!(
+            "Started Chrome with a debugging pipe. PID: {}",
+            process.0.id()
+        );
+
+        // Close the browser's ends on our side, so that we see EOF once it exits.
+        drop(browser_reads);
+        drop(browser_writes);
+
+        Ok(Self {
+            child_process: Arc::new(Mutex::new(process)),
+            debug_ws_url: None,
+            pipe: Some((to_browser, from_browser)),
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn start_process_with_pipe(_launch_options: &LaunchOptions) -> Result<Self, Error> {
+        Err(LaunchError::PipeUnsupported {}.into())
+    }
+
+    /// Builds the command to launch the browser with, lacking only the option telling it how
+    /// to expose the debugging protocol.
+    ///
+    /// Also returns the temporary profile directory, which has to outlive the process.
+    fn command(launch_options: &LaunchOptions) -> Result<(Command, Option<TempDir>), Error> {
         // NOTE: picking random data dir so that each a new browser instance is launched
         // (see man google-chrome)
-        let user_data_dir = ::tempfile::Builder::new()
-            .prefix("rust-headless-chrome-profile")
-            .tempdir()?;
-        let data_dir_option = format!("--user-data-dir={}", user_data_dir.path().to_str().unwrap());
+        let (user_data_dir, temporary_user_data_dir) =
+            if let Some(ref dir) = launch_options.user_data_dir {
+                (dir.clone(), None)
+            } else {
+                let dir = ::tempfile::Builder::new()
+                    .prefix("rust-headless-chrome-profile")
+                    .tempdir()?;
+                (dir.path().to_path_buf(), Some(dir))
+            };
+        let data_dir_option = format!(
+            "--user-data-dir={}",
+            user_data_dir
+                .to_str()
+                .ok_or_else(|| format_err!("User data dir must be valid UTF-8"))?
+        );
 
         trace!("Chrome will have profile: {}", data_dir_option);
 
-        let mut args = vec![
-            port_option.as_str(),
-            "--verbose",
-            "--no-first-run",
-            data_dir_option.as_str(),
-            //            "--window-size=1920,1080"
-        ];
+        let mut options = vec![data_dir_option];
+
+        if !launch_options.disable_default_args {
+            options.extend(DEFAULT_ARGS.iter().map(ToString::to_string));
+        }
 
         if launch_options.headless {
-            args.extend(&["--headless"]);
+            options.push("--headless".to_string());
+        }
+
+        if let Some((width, height)) = launch_options.window_size {
+            options.push(format!("--window-size={},{}", width, height));
+        }
+
+        if !launch_options.sandbox {
+            options.push("--no-sandbox".to_string());
+        }
+
+        if let Some(proxy_server) = launch_options.proxy_server {
+            options.push(format!("--proxy-server={}", proxy_server));
+        }
+
+        if let Some(proxy_bypass_list) = launch_options.proxy_bypass_list {
+            options.push(format!("--proxy-bypass-list={}", proxy_bypass_list));
         }
 
-        let extension_args: Vec<String> = launch_options
-            .extensions
-            .iter()
-            .map(|e| format!("--load-extension={}", e.to_str().unwrap()))
-            .collect();
+        options.extend(
+            launch_options
+                .extensions
+                .iter()
+                .map(|e| format!("--load-extension={}", e.to_str().unwrap())),
+        );
 
-        args.extend(extension_args.iter().map(String::as_str));
+        let mut args: Vec<&OsStr> = options.iter().map(OsStr::new).collect();
+        args.extend(&launch_options.args);
 
         let path = launch_options
             .path
@@ -183,14 +427,11 @@ impl Process {
             .ok_or_else(|| format_err!("Chrome path required"))?;
 
         info!("Launching Chrome binary at {:?}", &path);
+        trace!("Chrome arguments: {:?}", &args);
 
-        let process = TemporaryProcess(
-            Command::new(&path)
-                .args(&args)
-                .stderr(Stdio::piped())
-                .spawn()?,
-        );
-        Ok(process)
+        let mut command = Command::new(&path);
+        command.args(&args).envs(&launch_options.process_envs);
+        Ok((command, temporary_user_data_dir))
     }
 
     fn ws_url_from_reader<R>(reader: BufReader<R>) -> Result<Option<String>, Error>
@@ -212,7 +453,7 @@ impl Process {
             trace!("Chrome output: {}", chrome_output);
 
             if port_taken_re.is_match(&chrome_output) {
-                return Err(ChromeLaunchError::DebugPortInUse {}.into());
+                return Err(LaunchError::DebugPortInUse {}.into());
             }
 
             if let Some(answer) = extract(&chrome_output) {
@@ -241,11 +482,28 @@ impl Process {
         if let Ok(output_result) = chrome_output_result {
             output_result
         } else {
-            Err(ChromeLaunchError::PortOpenTimeout {}.into())
+            Err(LaunchError::PortOpenTimeout {}.into())
         }
     }
 }
 
+/// Creates an anonymous pipe, returning its read and write ends. Both are closed on exec.
+#[cfg(unix)]
+fn create_pipe() -> Result<(File, File), Error> {
+    use std::os::unix::io::FromRawFd;
+
+    let mut fds = [0; 2];
+    unsafe {
+        if libc::pipe(fds.as_mut_ptr()) != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        for fd in &fds {
+            libc::fcntl(*fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+        Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])))
+    }
+}
+
 fn get_available_port() -> Option<u16> {
     let mut ports: Vec<u16> = (8000..9000).collect();
     ports.shuffle(&mut thread_rng());
@@ -283,6 +541,79 @@ mod tests {
         info!("{:?}", chrome.debug_ws_url);
     }
 
+    #[test]
+    fn can_launch_chrome_with_custom_options() {
+        setup();
+        let user_data_dir = ::tempfile::Builder::new()
+            .prefix("rust-headless-chrome-test-profile")
+            .tempdir()
+            .unwrap();
+        let mut envs = HashMap::new();
+        envs.insert("TZ".to_string(), "Pacific/Auckland".to_string());
+        {
+            let _chrome = super::Process::new(
+                LaunchOptionsBuilder::default()
+                    .path(Some(default_executable().unwrap()))
+                    .window_size(Some((800, 600)))
+                    .args(vec![OsStr::new("--lang=de")])
+                    .disable_default_args(true)
+                    .process_envs(envs)
+                    .user_data_dir(Some(user_data_dir.path().to_path_buf()))
+                    .sandbox(false)
+                    .proxy_server(Some("socks5://127.0.0.1:1080"))
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        }
+        // Chrome should have used (and left behind) the given profile directory
+        assert!(user_data_dir.path().read_dir().unwrap().next().is_some());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn talks_to_browser_over_fds_3_and_4() {
+        use std::io::Write;
+        use std::os::unix::fs::PermissionsExt;
+
+        setup();
+        // A stand-in for Chrome which echoes the first message it reads from fd 3 to fd 4, then
+        // exits
+        let dir = ::tempfile::tempdir().unwrap();
+        let fake_browser = dir.path().join("fake-browser");
+        std::fs::write(
+            &fake_browser,
+            "#!/bin/sh\nhead -c 8 <&3 >&4\nprintf '\\0' >&4\nexit 3\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_browser, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut process = super::Process::new(
+            LaunchOptionsBuilder::default()
+                .path(Some(fake_browser))
+                .remote_debugging_pipe(true)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(process.debug_ws_url.is_none());
+
+        let (mut to_browser, from_browser) = process.take_pipe().unwrap();
+        assert!(process.take_pipe().is_none());
+        to_browser.write_all(b"{\"id\":0}\0").unwrap();
+
+        let mut echoed = Vec::new();
+        BufReader::new(from_browser)
+            .read_until(0, &mut echoed)
+            .unwrap();
+        assert_eq!(&b"{\"id\":0}\0"[..], &echoed[..]);
+
+        let exit_status = util::Wait::with_timeout(Duration::from_secs(5))
+            .until(process.exit_probe())
+            .unwrap();
+        assert_eq!(Some(3), exit_status.code());
+    }
+
     #[test]
     fn handle_errors_in_chrome_output() {
         setup();
@@ -0,0 +1,384 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use log::*;
+
+use super::process::LaunchOptions;
+use super::transport::DisconnectReason;
+use super::Browser;
+use crate::Error;
+
+/// How a `SupervisedBrowser` goes about relaunching a browser which went away.
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// How long to wait before the first attempt at relaunching the browser. Defaults to 1 second.
+    pub initial_backoff: Duration,
+    /// The longest we'll wait between attempts, however many have failed. Defaults to 1 minute.
+    pub max_backoff: Duration,
+    /// What the wait is multiplied by after each failed attempt. Defaults to 2.
+    pub multiplier: u32,
+    /// How many attempts in a row to make before giving up, or `None` to keep trying forever.
+    /// Defaults to `None`.
+    pub max_attempts: Option<u32>,
+    /// Whether to reopen the tabs the browser had open, at the URLs they were last at.
+    /// Defaults to true.
+    pub reopen_tabs: bool,
+    /// Whether to relaunch a browser which was shut down for going quiet for longer than its
+    /// `idle_browser_timeout`, rather than crashing or exiting. Defaults to false, as a browser
+    /// which is merely left alone would otherwise be relaunched over and over.
+    pub restart_when_idle: bool,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            multiplier: 2,
+            max_attempts: None,
+            reopen_tabs: true,
+            restart_when_idle: false,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// How long to wait before the given attempt (counting from 0) at relaunching.
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(self.multiplier.saturating_pow(attempt))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+/// What happened when a `SupervisedBrowser` relaunched its browser.
+#[derive(Debug, Clone)]
+pub struct Restart {
+    /// Why the previous browser went away.
+    pub reason: DisconnectReason,
+    /// How many attempts it took to launch the new one.
+    pub attempts: u32,
+    /// The URLs of the tabs which were reopened in the new browser.
+    pub reopened_urls: Vec<String>,
+    /// The URLs of the tabs which weren't, either because they failed to reopen or because
+    /// `RestartPolicy::reopen_tabs` is off.
+    pub lost_urls: Vec<String>,
+}
+
+type Launcher = Box<dyn Fn() -> Result<Browser, Error> + Send + Sync>;
+type RestartCallback = Box<dyn Fn(&Restart) + Send>;
+
+/// A `Browser` which is relaunched whenever it crashes, exits or otherwise disconnects (though
+/// by default not when it's shut down for being idle), for long-running programs which can't
+/// afford to go down with it.
+///
+/// Handles to the old browser and its tabs stop working once it goes away, so get hold of the
+/// current one with `browser()` rather than keeping it around.
+///
+/// ```rust,no_run
+/// # use failure::Error;
+/// # fn main() -> Result<(), Error> {
+/// #
+/// use headless_chrome::LaunchOptionsBuilder;
+/// use headless_chrome::browser::{RestartPolicy, SupervisedBrowser};
+///
+/// let supervisor = SupervisedBrowser::new(
+///     LaunchOptionsBuilder::default().build().unwrap(),
+///     RestartPolicy::default(),
+/// )?;
+/// supervisor.on_restart(|restart| println!("Lost tabs at {:?}", restart.lost_urls));
+///
+/// let tab = supervisor.browser().wait_for_initial_tab()?;
+/// tab.navigate_to("https://www.wikipedia.org")?;
+/// #
+/// # Ok(())
+/// # }
+/// ```
+pub struct SupervisedBrowser {
+    state: Arc<State>,
+}
+
+struct State {
+    launch: Launcher,
+    policy: RestartPolicy,
+    browser: Mutex<Arc<Browser>>,
+    /// Bumped each time the browser is replaced, so we only ever restart the current one.
+    generation: AtomicUsize,
+    restarts: AtomicUsize,
+    failed_attempts: AtomicUsize,
+    given_up: AtomicBool,
+    callbacks: Mutex<Vec<RestartCallback>>,
+}
+
+impl SupervisedBrowser {
+    /// Launch a browser with the given options, and relaunch it with the same ones whenever it
+    /// goes away.
+    pub fn new(
+        launch_options: LaunchOptions<'static>,
+        policy: RestartPolicy,
+    ) -> Result<Self, Error> {
+        Self::with_launcher(move || Browser::new(launch_options.clone()), policy)
+    }
+
+    /// Like `new`, but (re)launching the browser by calling `launch`, e.g. to connect to a
+    /// [MockBrowser](transport/struct.MockBrowser.html) in tests.
+    pub fn with_launcher<F>(launch: F, policy: RestartPolicy) -> Result<Self, Error>
+    where
+        F: Fn() -> Result<Browser, Error> + Send + Sync + 'static,
+    {
+        let browser = Arc::new(launch()?);
+        let state = Arc::new(State {
+            launch: Box::new(launch),
+            policy,
+            browser: Mutex::new(Arc::clone(&browser)),
+            generation: AtomicUsize::new(0),
+            restarts: AtomicUsize::new(0),
+            failed_attempts: AtomicUsize::new(0),
+            given_up: AtomicBool::new(false),
+            callbacks: Mutex::new(vec![]),
+        });
+        State::watch(&state, &browser, 0);
+        Ok(Self { state })
+    }
+
+    /// The current browser.
+    pub fn browser(&self) -> Arc<Browser> {
+        Arc::clone(&self.state.browser.lock().unwrap())
+    }
+
+    /// How many times the browser has been relaunched.
+    pub fn restart_count(&self) -> usize {
+        self.state.restarts.load(Ordering::SeqCst)
+    }
+
+    /// How many attempts at relaunching the browser have failed, in total.
+    pub fn failed_restart_count(&self) -> usize {
+        self.state.failed_attempts.load(Ordering::SeqCst)
+    }
+
+    /// Whether we've stopped trying to relaunch the browser, having made
+    /// `RestartPolicy::max_attempts` attempts in a row.
+    pub fn has_given_up(&self) -> bool {
+        self.state.given_up.load(Ordering::SeqCst)
+    }
+
+    /// Call `callback` each time the browser has been relaunched.
+    pub fn on_restart<F>(&self, callback: F)
+    where
+        F: Fn(&Restart) + Send + 'static,
+    {
+        self.state
+            .callbacks
+            .lock()
+            .unwrap()
+            .push(Box::new(callback));
+    }
+}
+
+impl State {
+    /// Restart `browser` once it goes away, unless we're gone by then.
+    fn watch(state: &Arc<Self>, browser: &Browser, generation: usize) {
+        let state = Arc::downgrade(state);
+        browser.on_disconnect(move |reason| {
+            let state = Weak::clone(&state);
+            let reason = reason.clone();
+            thread::spawn(move || Self::restart(&state, generation, reason));
+        });
+    }
+
+    fn restart(state: &Weak<Self>, generation: usize, reason: DisconnectReason) {
+        let (policy, urls) = match state.upgrade() {
+            Some(state) => {
+                if state.generation.load(Ordering::SeqCst) != generation {
+                    return;
+                }
+                let browser = state.browser.lock().unwrap();
+                let urls: Vec<String> = browser
+                    .get_tabs()
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|tab| tab.get_url())
+                    .collect();
+                (state.policy.clone(), urls)
+            }
+            None => return,
+        };
+        if reason == DisconnectReason::Idle && !policy.restart_when_idle {
+            info!("Not restarting browser, as {}", reason);
+            return;
+        }
+        info!("Restarting browser, as {}", reason);
+
+        let mut attempts = 0;
+        loop {
+            let out_of_attempts = match policy.max_attempts {
+                Some(max) => attempts >= max,
+                None => false,
+            };
+            if out_of_attempts {
+                error!(
+                    "Giving up on restarting browser after {} attempts",
+                    attempts
+                );
+                if let Some(state) = state.upgrade() {
+                    state.given_up.store(true, Ordering::SeqCst);
+                }
+                return;
+            }
+            thread::sleep(policy.backoff(attempts));
+            attempts += 1;
+
+            // stop if the supervisor was dropped in the meantime
+            let state = match state.upgrade() {
+                Some(state) => state,
+                None => return,
+            };
+            match (state.launch)() {
+                Ok(browser) => {
+                    state.replace(browser, reason, attempts, urls);
+                    return;
+                }
+                Err(error) => {
+                    warn!(
+                        "Attempt {} at restarting browser failed: {}",
+                        attempts, error
+                    );
+                    state.failed_attempts.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        }
+    }
+
+    fn replace(
+        self: &Arc<Self>,
+        browser: Browser,
+        reason: DisconnectReason,
+        attempts: u32,
+        urls: Vec<String>,
+    ) {
+        let (reopened_urls, lost_urls) = if self.policy.reopen_tabs {
+            reopen_tabs(&browser, urls)
+        } else {
+            (vec![], urls)
+        };
+
+        let browser = Arc::new(browser);
+        *self.browser.lock().unwrap() = Arc::clone(&browser);
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.restarts.fetch_add(1, Ordering::SeqCst);
+        self.given_up.store(false, Ordering::SeqCst);
+        Self::watch(self, &browser, generation);
+
+        let restart = Restart {
+            reason,
+            attempts,
+            reopened_urls,
+            lost_urls,
+        };
+        info!("Restarted browser: {:?}", restart);
+        for callback in self.callbacks.lock().unwrap().iter() {
+            callback(&restart);
+        }
+    }
+}
+
+/// Opens a tab in `browser` for each of `urls`, starting with the one it launched with.
+/// Returns the URLs which could be reopened, and those which couldn't.
+fn reopen_tabs(browser: &Browser, urls: Vec<String>) -> (Vec<String>, Vec<String>) {
+    let mut reopened_urls = vec![];
+    let mut lost_urls = vec![];
+    for (i, url) in urls.into_iter().enumerate() {
+        let tab = if i == 0 {
+            browser.wait_for_initial_tab()
+        } else {
+            browser.new_tab()
+        };
+        let reopened = tab.and_then(|tab| {
+            if url != "about:blank" {
+                tab.navigate_to(&url)?;
+            }
+            Ok(())
+        });
+        match reopened {
+            Ok(()) => reopened_urls.push(url),
+            Err(error) => {
+                warn!("Couldn't reopen tab at {}: {}", url, error);
+                lost_urls.push(url);
+            }
+        }
+    }
+    (reopened_urls, lost_urls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::browser::transport::MockBrowser;
+
+    /// A supervisor of mock browsers which go idle after 100ms, and how many it has launched.
+    fn idling_supervisor(policy: RestartPolicy) -> (SupervisedBrowser, Arc<AtomicUsize>) {
+        let launches = Arc::new(AtomicUsize::new(0));
+        let counted_launches = Arc::clone(&launches);
+        let supervisor = SupervisedBrowser::with_launcher(
+            move || {
+                counted_launches.fetch_add(1, Ordering::SeqCst);
+                let mock = MockBrowser::new();
+                Browser::with_connection_and_idle_timeout(
+                    |messages_tx| Ok(mock.connect(messages_tx)),
+                    Duration::from_millis(100),
+                )
+            },
+            RestartPolicy {
+                initial_backoff: Duration::from_millis(10),
+                ..policy
+            },
+        )
+        .unwrap();
+        (supervisor, launches)
+    }
+
+    #[test]
+    fn leaves_idle_browsers_alone() {
+        let (supervisor, launches) = idling_supervisor(RestartPolicy::default());
+        let (reasons_tx, reasons_rx) = std::sync::mpsc::channel();
+        supervisor
+            .browser()
+            .on_disconnect(move |reason| reasons_tx.send(reason.clone()).unwrap());
+        assert_eq!(
+            DisconnectReason::Idle,
+            reasons_rx.recv_timeout(Duration::from_secs(5)).unwrap()
+        );
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(1, launches.load(Ordering::SeqCst));
+        assert_eq!(0, supervisor.restart_count());
+
+        let (supervisor, _) = idling_supervisor(RestartPolicy {
+            restart_when_idle: true,
+            ..RestartPolicy::default()
+        });
+        crate::util::Wait::with_timeout(Duration::from_secs(5))
+            .until(|| Some(()).filter(|_| supervisor.restart_count() > 0))
+            .unwrap();
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let policy = RestartPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            ..RestartPolicy::default()
+        };
+        let backoffs: Vec<_> = (0..6).map(|attempt| policy.backoff(attempt)).collect();
+        assert_eq!(
+            vec![100, 200, 400, 800, 1000, 1000],
+            backoffs
+                .iter()
+                .map(|backoff| backoff.as_millis())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(policy.max_backoff, policy.backoff(u32::max_value()));
+    }
+}
@@ -1,12 +1,25 @@
-use failure::Error;
+use failure::Fail;
 use log::*;
 
 use super::point::Point;
 use crate::protocol::dom;
 use crate::protocol::page;
 use crate::protocol::runtime;
+use crate::Error;
 use std::collections::HashMap;
 
+/// How many intermediate `mouseMoved` events `Element::drag_to` dispatches.
+const DRAG_STEPS: u32 = 10;
+
+#[derive(Debug, Fail)]
+#[fail(display = "JavaScript exception: {}", description)]
+pub struct JsException {
+    pub description: String,
+    /// Where it was thrown, which `Tab::original_stack_trace` can map back to the original
+    /// sources.
+    pub stack_trace: Option<runtime::methods::StackTrace>,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct ElementQuad {
     pub top_left: Point,
@@ -253,6 +266,85 @@ impl<'a> Element<'a> {
         Ok(self)
     }
 
+    /// Move the mouse over the middle of this element, triggering `mouseover` and friends.
+    pub fn hover(&self) -> Result<&Self, Error> {
+        debug!(
+            "Hovering over element found via {}",
+            self.found_via_selector
+        );
+
+        let midpoint = self.get_midpoint()?;
+        self.parent.move_mouse_to(midpoint)?;
+        Ok(self)
+    }
+
+    /// Double click the middle of this element, triggering `dblclick`.
+    pub fn double_click(&self) -> Result<&Self, Error> {
+        debug!(
+            "Double-clicking element found via {}",
+            self.found_via_selector
+        );
+
+        let midpoint = self.get_midpoint()?;
+        self.parent.double_click_point(midpoint)?;
+        Ok(self)
+    }
+
+    /// Right click the middle of this element, triggering `contextmenu`.
+    pub fn right_click(&self) -> Result<&Self, Error> {
+        debug!(
+            "Right-clicking element found via {}",
+            self.found_via_selector
+        );
+
+        let midpoint = self.get_midpoint()?;
+        self.parent.right_click_point(midpoint)?;
+        Ok(self)
+    }
+
+    /// Click the middle of this element with the middle mouse button, triggering `auxclick`.
+    pub fn middle_click(&self) -> Result<&Self, Error> {
+        debug!(
+            "Middle-clicking element found via {}",
+            self.found_via_selector
+        );
+
+        let midpoint = self.get_midpoint()?;
+        self.parent.middle_click_point(midpoint)?;
+        Ok(self)
+    }
+
+    /// Drag this element onto `target`, from midpoint to midpoint.
+    pub fn drag_to(&self, target: &Element) -> Result<&Self, Error> {
+        debug!(
+            "Dragging element found via {} to element found via {}",
+            self.found_via_selector, target.found_via_selector
+        );
+
+        let from = self.get_midpoint()?;
+        let to = target.get_midpoint()?;
+        self.parent.drag(from, to, DRAG_STEPS)?;
+        Ok(self)
+    }
+
+    /// Tap the middle of this element with one finger.
+    ///
+    /// Pages usually only react to this as a touch once `Tab::set_touch_emulation` is enabled.
+    pub fn tap(&self) -> Result<&Self, Error> {
+        debug!("Tapping element found via {}", self.found_via_selector);
+
+        let midpoint = self.get_midpoint()?;
+        self.parent.tap_point(midpoint)?;
+        Ok(self)
+    }
+
+    /// Hover over this element and turn the mouse wheel, e.g. to scroll a scrollable `<div>`.
+    pub fn scroll_wheel(&self, delta_x: f64, delta_y: f64) -> Result<&Self, Error> {
+        self.hover()?;
+        self.parent.scroll_wheel(delta_x, delta_y)?;
+        Ok(self)
+    }
+
     pub fn type_into(&self, text: &str) -> Result<&Self, Error> {
         self.click()?;
 
@@ -275,6 +367,7 @@ impl<'a> Element<'a> {
             .call_method(runtime::methods::CallFunctionOn {
                 object_id: &self.remote_object_id,
                 function_declaration,
+                arguments: None,
                 return_by_value: false,
                 generate_preview: true,
                 silent: false,
@@ -284,6 +377,186 @@ impl<'a> Element<'a> {
         Ok(result)
     }
 
+    /// Call a JS function with `this` bound to the element and the given JSON-serializable
+    /// arguments, returning its result by value.
+    ///
+    /// Exceptions thrown by the function are turned into an `Err`.
+    fn call_js_fn_by_value(
+        &self,
+        function_declaration: &str,
+        arguments: Vec<serde_json::Value>,
+    ) -> Result<serde_json::Value, Error> {
+        let return_object = self.parent.call_method(runtime::methods::CallFunctionOn {
+            object_id: &self.remote_object_id,
+            function_declaration,
+            arguments: Some(
+                arguments
+                    .into_iter()
+                    .map(|value| runtime::methods::CallArgument { value })
+                    .collect(),
+            ),
+            return_by_value: true,
+            generate_preview: false,
+            silent: false,
+        })?;
+
+        if let Some(exception_details) = return_object.exception_details {
+            let stack_trace = exception_details.stack_trace;
+            let description = exception_details
+                .exception
+                .and_then(|exception| exception.description)
+                .unwrap_or(exception_details.text);
+            return Err(JsException {
+                description,
+                stack_trace,
+            }
+            .into());
+        }
+
+        Ok(return_object
+            .result
+            .value
+            .unwrap_or(serde_json::Value::Null))
+    }
+
+    /// Select the `<option>`s of this `<select>` element whose values are in `values`,
+    /// deselecting all others, and fire the `input` and `change` events a user's choice would.
+    ///
+    /// For a `<select>` without the `multiple` attribute, only the first match is selected.
+    /// Returns the values which actually ended up being selected.
+    pub fn select_options(&self, values: &[&str]) -> Result<Vec<String>, Error> {
+        debug!(
+            "Selecting options {:?} of element found via {}",
+            values, self.found_via_selector
+        );
+
+        let selected = self.call_js_fn_by_value(
+            r#"function(values) {
+                if (this.nodeName.toLowerCase() !== "select") {
+                    throw new Error("Element is not a <select> element");
+                }
+                let found = false;
+                for (const option of this.options) {
+                    option.selected = !found && values.includes(option.value);
+                    found = found || (option.selected && !this.multiple);
+                }
+                this.dispatchEvent(new Event("input", { bubbles: true }));
+                this.dispatchEvent(new Event("change", { bubbles: true }));
+                return Array.from(this.selectedOptions).map(option => option.value);
+            }"#,
+            vec![values.into()],
+        )?;
+
+        Ok(serde_json::from_value(selected)?)
+    }
+
+    /// Check this checkbox or radio button, unless it already is.
+    ///
+    /// The element is clicked through JS rather than with the mouse, so this also works for
+    /// inputs hidden behind custom styling.
+    pub fn check(&self) -> Result<&Self, Error> {
+        self.set_checked(true)
+    }
+
+    /// Uncheck this checkbox, unless it already is.
+    pub fn uncheck(&self) -> Result<&Self, Error> {
+        self.set_checked(false)
+    }
+
+    fn set_checked(&self, checked: bool) -> Result<&Self, Error> {
+        debug!(
+            "Setting checked to {} for element found via {}",
+            checked, self.found_via_selector
+        );
+
+        self.call_js_fn_by_value(
+            r#"function(checked) {
+                if (this.nodeName.toLowerCase() !== "input" ||
+                    (this.type !== "checkbox" && this.type !== "radio")) {
+                    throw new Error("Element is not a checkbox or radio button");
+                }
+                if (this.checked !== checked) {
+                    this.click();
+                }
+                if (this.checked !== checked) {
+                    throw new Error("Clicking the element did not change its state");
+                }
+            }"#,
+            vec![checked.into()],
+        )?;
+        Ok(self)
+    }
+
+    /// Empty this `<input>`, `<textarea>` or contenteditable element, firing the `input` and
+    /// `change` events deleting its contents by hand would.
+    pub fn clear(&self) -> Result<&Self, Error> {
+        debug!("Clearing element found via {}", self.found_via_selector);
+
+        self.call_js_fn_by_value(
+            r#"function() {
+                const name = this.nodeName.toLowerCase();
+                if (name === "input" || name === "textarea") {
+                    this.value = "";
+                } else if (this.isContentEditable) {
+                    this.textContent = "";
+                } else {
+                    throw new Error("Element is not an <input>, <textarea> or contenteditable");
+                }
+                this.dispatchEvent(new Event("input", { bubbles: true }));
+                this.dispatchEvent(new Event("change", { bubbles: true }));
+            }"#,
+            vec![],
+        )?;
+        Ok(self)
+    }
+
+    /// Give this form control the given value the way a user would, depending on what kind of
+    /// control it is:
+    ///
+    /// * `<select>`: the option with that value is selected
+    /// * checkboxes: checked if `value` is one of `true`, `on`, `yes`, `1` or `checked`,
+    ///   unchecked otherwise
+    /// * radio buttons: checked if `value` is one of those or the radio button's own value, so a
+    ///   group can be filled by giving each of its buttons the value to pick; left alone
+    ///   otherwise, as only checking another button in its group can uncheck one
+    /// * file inputs: `value` is the path of the file to upload
+    /// * anything else: the element is cleared and `value` is typed into it
+    pub fn fill(&self, value: &str) -> Result<&Self, Error> {
+        let description = self.get_description()?;
+        let attribute = |name: &str| {
+            description
+                .attributes
+                .as_ref()
+                .and_then(|attributes| attributes.get(name))
+        };
+        let input_type = attribute("type")
+            .map(|input_type| input_type.to_lowercase())
+            .unwrap_or_default();
+        let checked =
+            ["true", "on", "yes", "1", "checked"].contains(&value.to_lowercase().as_str());
+
+        match (description.local_name.as_str(), input_type.as_str()) {
+            ("select", _) => {
+                self.select_options(&[value])?;
+            }
+            ("input", "checkbox") => {
+                self.set_checked(checked)?;
+            }
+            ("input", "radio") => {
+                if checked || attribute("value").map(String::as_str) == Some(value) {
+                    self.set_checked(true)?;
+                }
+            }
+            ("input", "file") => {
+                self.set_input_files(&[value])?;
+            }
+            _ => {
+                self.clear()?.type_into(value)?;
+            }
+        }
+        Ok(self)
+    }
+
     pub fn focus(&self) -> Result<&Self, Error> {
         self.parent.call_method(dom::methods::Focus {
             backend_node_id: Some(self.backend_node_id),
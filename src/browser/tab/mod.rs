@@ -1,29 +1,50 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
 
-use failure::{Error, Fail};
+use failure::{format_err, Fail};
 use log::*;
 use serde;
 
 use element::Element;
-use point::Point;
+pub use point::Point;
+pub use stream::IoStream;
 
 use crate::browser::Transport;
+use crate::coverage::StyleSheetCoverage;
+use crate::protocol::css::StyleSheetHeader;
+use crate::protocol::debugger::events::ScriptParsedParams;
+use crate::protocol::input::{Modifier, MouseButton};
 use crate::protocol::page::methods::Navigate;
+use crate::protocol::runtime::methods::StackTrace;
 use crate::protocol::target::TargetId;
 use crate::protocol::target::TargetInfo;
+use crate::protocol::tracing::events::TracingCompleteParams;
+use crate::protocol::tracing::{TraceConfig, TransferMode};
 use crate::protocol::Event;
-use crate::protocol::{dom, input, page, profiler, target};
-use crate::{protocol, util};
+use crate::protocol::{
+    css, debugger, dom, emulation, input, inspector, network, page, profiler, runtime, target,
+    tracing,
+};
+use crate::source_map::{OriginalLocation, SourceMap};
+use crate::{protocol, util, Error};
 
-use super::transport::SessionId;
+use super::transport::{MethodDestination, SessionId};
 use crate::protocol::dom::Node;
 use std::time::Duration;
 
 pub mod element;
 mod keys;
 mod point;
+mod stream;
+
+/// How many fingers the page is told it can expect when touch emulation is enabled.
+const MAX_TOUCH_POINTS: u32 = 5;
+
+/// Source maps fetched for the tab's scripts, by script URL and source map URL.
+type SourceMaps = HashMap<(String, String), Arc<SourceMap>>;
 
 /// A handle to a single page. Exposes methods for simulating user actions (clicking,
 /// typing), and also for getting information about the DOM and other parts of the page.
@@ -34,18 +55,34 @@ pub struct Tab {
     session_id: SessionId,
     navigating: Arc<AtomicBool>,
     target_info: Arc<Mutex<TargetInfo>>,
+    mouse_position: Mutex<Point>,
+    pressed_keys: Mutex<keys::PressedKeys>,
+    typing_delay: Mutex<Duration>,
+    call_timeout: Mutex<Option<Duration>>,
+    debugger_enabled: AtomicBool,
+    scripts: Arc<Mutex<HashMap<String, ScriptParsedParams>>>,
+    style_sheets: Arc<Mutex<HashMap<String, StyleSheetHeader>>>,
+    source_maps: Arc<Mutex<SourceMaps>>,
+    trace: Arc<Mutex<Trace>>,
+}
+
+/// What the tab has told us about the trace it's recording, if any.
+#[derive(Debug, Default)]
+struct Trace {
+    events: Vec<serde_json::Value>,
+    complete: Option<TracingCompleteParams>,
 }
 
 #[derive(Debug, Fail)]
 #[fail(display = "No element found for selector: {}", selector)]
 pub struct NoElementFound {
-    selector: String,
+    pub selector: String,
 }
 
 #[derive(Debug, Fail)]
 #[fail(display = "Navigate failed: {}", error_text)]
 pub struct NavigationFailed {
-    error_text: String,
+    pub error_text: String,
 }
 
 impl Tab {
@@ -70,9 +107,20 @@ impl Tab {
             session_id,
             navigating: Arc::new(AtomicBool::new(false)),
             target_info: target_info_mutex,
+            mouse_position: Mutex::new(Point::default()),
+            pressed_keys: Mutex::new(keys::PressedKeys::default()),
+            typing_delay: Mutex::new(Duration::from_millis(0)),
+            call_timeout: Mutex::new(None),
+            debugger_enabled: AtomicBool::new(false),
+            scripts: Arc::new(Mutex::new(HashMap::new())),
+            style_sheets: Arc::new(Mutex::new(HashMap::new())),
+            source_maps: Arc::new(Mutex::new(HashMap::new())),
+            trace: Arc::new(Mutex::new(Trace::default())),
         };
 
         tab.call_method(page::methods::Enable {})?;
+        // so we hear about the tab crashing
+        tab.call_method(inspector::methods::Enable {})?;
         tab.call_method(page::methods::SetLifecycleEventsEnabled { enabled: true })?;
 
         tab.start_event_handler_thread();
@@ -94,43 +142,118 @@ impl Tab {
         info.url.clone()
     }
 
+    /// Whether the tab has crashed (e.g. its renderer process died or ran out of memory), after
+    /// which all calls on it fail with `TargetCrashed`.
+    pub fn is_crashed(&self) -> bool {
+        self.transport.is_crashed(&self.session_id)
+    }
+
     fn start_event_handler_thread(&self) {
         let incoming_events_rx = self
             .transport
             .listen_to_target_events(self.session_id.clone());
         let navigating = Arc::clone(&self.navigating);
+        let scripts = Arc::clone(&self.scripts);
+        let style_sheets = Arc::clone(&self.style_sheets);
+        let source_maps = Arc::clone(&self.source_maps);
+        let recorded_trace = Arc::clone(&self.trace);
 
         std::thread::spawn(move || {
             for event in incoming_events_rx {
                 trace!("{:?}", &event);
-                if let Event::Lifecycle(lifecycle_event) = event {
-                    //                        if lifecycle_event.params.frame_id == main_frame_id {
-                    match lifecycle_event.params.name.as_ref() {
-                        "networkAlmostIdle" => {
-                            navigating.store(false, Ordering::SeqCst);
-                        }
-                        "init" => {
-                            navigating.store(true, Ordering::SeqCst);
+                match event {
+                    Event::Lifecycle(lifecycle_event) => {
+                        //                        if lifecycle_event.params.frame_id == main_frame_id {
+                        match lifecycle_event.params.name.as_ref() {
+                            "networkAlmostIdle" => {
+                                navigating.store(false, Ordering::SeqCst);
+                            }
+                            "init" => {
+                                navigating.store(true, Ordering::SeqCst);
+                            }
+                            _ => {}
                         }
-                        _ => {}
                     }
+                    // the page's scripts and style sheets are gone, along with any reason to
+                    // keep what we know about them
+                    Event::FrameNavigated(frame_navigated_event)
+                        if frame_navigated_event.params.frame.parent_id.is_none() =>
+                    {
+                        scripts.lock().unwrap().clear();
+                        style_sheets.lock().unwrap().clear();
+                        source_maps.lock().unwrap().clear();
+                    }
+                    Event::ScriptParsed(script_parsed_event) => {
+                        let script = script_parsed_event.params;
+                        scripts
+                            .lock()
+                            .unwrap()
+                            .insert(script.script_id.clone(), script);
+                    }
+                    Event::StyleSheetAdded(style_sheet_added_event) => {
+                        let header = style_sheet_added_event.params.header;
+                        style_sheets
+                            .lock()
+                            .unwrap()
+                            .insert(header.style_sheet_id.clone(), header);
+                    }
+                    Event::DataCollected(data_collected_event) => {
+                        recorded_trace
+                            .lock()
+                            .unwrap()
+                            .events
+                            .extend(data_collected_event.params.value);
+                    }
+                    Event::TracingComplete(tracing_complete_event) => {
+                        recorded_trace.lock().unwrap().complete =
+                            Some(tracing_complete_event.params);
+                    }
+                    _ => {}
                 }
             }
         });
     }
 
+    /// Make a protocol call on this tab, waiting as long as `set_default_timeout` says for a
+    /// response (or as long as the browser's default, if it hasn't been called).
     pub fn call_method<C>(&self, method: C) -> Result<C::ReturnObject, Error>
+    where
+        C: protocol::Method + serde::Serialize + std::fmt::Debug,
+    {
+        self.call_method_with_timeout(method, self.call_timeout())
+    }
+
+    fn call_timeout(&self) -> Duration {
+        self.call_timeout
+            .lock()
+            .unwrap()
+            .unwrap_or_else(|| self.transport.call_timeout())
+    }
+
+    /// Make a protocol call which fails with a `MethodTimeout` error unless answered within
+    /// `timeout`, e.g. for slow operations like printing a long page to PDF.
+    pub fn call_method_with_timeout<C>(
+        &self,
+        method: C,
+        timeout: Duration,
+    ) -> Result<C::ReturnObject, Error>
     where
         C: protocol::Method + serde::Serialize + std::fmt::Debug,
     {
         debug!("Calling method: {:?}", method);
-        let result = self
-            .transport
-            .call_method_on_target(self.session_id.clone(), method);
-        let mut result_string = format!("{:?}", result);
-        result_string.truncate(70);
-        debug!("Got result: {:?}", result_string);
-        result
+        let result = self.transport.call_method_with_timeout(
+            method,
+            MethodDestination::Target(self.session_id.clone()),
+            timeout,
+        );
+        trace!("Got result: {:?}", result);
+        result.map_err(Into::into)
+    }
+
+    /// How long calls on this tab wait for a response, overriding the browser's default.
+    pub fn set_default_timeout(&self, timeout: Duration) -> &Self {
+        *self.call_timeout.lock().unwrap() = Some(timeout);
+        self
     }
 
     pub fn wait_until_navigated(&self) -> Result<&Self, Error> {
@@ -257,6 +380,41 @@ impl Tab {
         Ok(elements)
     }
 
+    /// Fill in several form controls at once, given pairs of a selector and the value to give
+    /// the element it matches.
+    ///
+    /// See `Element::fill` for how each kind of control is dealt with. The pairs are filled in
+    /// the order they're iterated over.
+    ///
+    /// ```rust,no_run
+    /// # use failure::Error;
+    /// # fn main() -> Result<(), Error> {
+    /// #
+    /// # use headless_chrome::{Browser, LaunchOptionsBuilder};
+    /// # let browser = Browser::new(LaunchOptionsBuilder::default().build().unwrap())?;
+    /// # let tab = browser.wait_for_initial_tab()?;
+    /// let mut fields = std::collections::HashMap::new();
+    /// fields.insert("input#name", "Alice");
+    /// fields.insert("select#country", "NZ");
+    /// fields.insert("input#newsletter", "true");
+    /// tab.fill_form(&fields)?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn fill_form<I, S, V>(&self, fields: I) -> Result<&Self, Error>
+    where
+        I: IntoIterator<Item = (S, V)>,
+        S: AsRef<str>,
+        V: AsRef<str>,
+    {
+        for (selector, value) in fields {
+            self.wait_for_element(selector.as_ref())?
+                .fill(value.as_ref())?;
+        }
+        Ok(self)
+    }
+
     pub fn describe_node(&self, node_id: dom::NodeId) -> Result<dom::Node, Error> {
         let node = self
             .call_method(dom::methods::DescribeNode {
@@ -268,28 +426,123 @@ impl Tab {
         Ok(node)
     }
 
+    /// Type a string key by key, as a user would.
+    ///
+    /// Characters which aren't on a US keyboard (e.g. emoji or CJK) are inserted with
+    /// `insert_text` instead. Between keys, this waits for the delay set by `set_typing_delay`.
     pub fn type_str(&self, string_to_type: &str) -> Result<&Self, Error> {
+        let delay = *self.typing_delay.lock().unwrap();
         for c in string_to_type.split("") {
             // split call above will have empty string at start and end which we won't type
             if c == "" {
                 continue;
             }
-            self.press_key(c)?;
+            if keys::get_key_definition(c).is_ok() {
+                self.press_key(c)?;
+            } else {
+                self.insert_text(c)?;
+            }
+            if delay > Duration::from_millis(0) {
+                std::thread::sleep(delay);
+            }
         }
         Ok(self)
     }
 
+    /// Set how long `type_str` (and thereby `Element::type_into`) pauses after each key.
+    ///
+    /// Defaults to no delay; some pages (e.g. autocompletes with debouncing) only behave
+    /// realistically when typed into at human speed.
+    pub fn set_typing_delay(&self, delay: Duration) -> &Self {
+        *self.typing_delay.lock().unwrap() = delay;
+        self
+    }
+
+    /// Insert text into the focused element in one go, without dispatching any key events.
+    ///
+    /// This is much faster than `type_str` and works for arbitrary Unicode, but pages listening
+    /// for `keydown` and friends won't notice it; only `input` events are fired.
+    pub fn insert_text(&self, text: &str) -> Result<&Self, Error> {
+        self.call_method(input::methods::InsertText { text })?;
+        Ok(self)
+    }
+
+    /// Set the text of the current IME composition, as an input method editor does while the
+    /// user is composing e.g. CJK characters.
+    ///
+    /// `selection_start` and `selection_end` are the cursor / selection within `text`. Use
+    /// `commit_composition` to finish composing, or an empty `text` to cancel.
+    ///
+    /// ```rust,no_run
+    /// # use failure::Error;
+    /// # fn main() -> Result<(), Error> {
+    /// #
+    /// # use headless_chrome::{Browser, LaunchOptionsBuilder};
+    /// # let browser = Browser::new(LaunchOptionsBuilder::default().build().unwrap())?;
+    /// # let tab = browser.wait_for_initial_tab()?;
+    /// tab.wait_for_element("input")?.click()?;
+    /// tab.set_composition("n", 1, 1)?
+    ///     .set_composition("に", 1, 1)?
+    ///     .set_composition("にほ", 2, 2)?
+    ///     .commit_composition("日本")?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_composition(
+        &self,
+        text: &str,
+        selection_start: u32,
+        selection_end: u32,
+    ) -> Result<&Self, Error> {
+        self.call_method(input::methods::ImeSetComposition {
+            text,
+            selection_start,
+            selection_end,
+            replacement_start: None,
+            replacement_end: None,
+        })?;
+        Ok(self)
+    }
+
+    /// Finish the current IME composition by committing `text` into the focused element.
+    pub fn commit_composition(&self, text: &str) -> Result<&Self, Error> {
+        self.insert_text(text)
+    }
+
+    /// Press and release a single key, with whatever modifiers are currently held down.
     pub fn press_key(&self, key: &str) -> Result<&Self, Error> {
+        self.key_down(key)?;
+        self.key_up(key)
+    }
+
+    /// Press a key and keep it held down until `key_up` is called.
+    ///
+    /// Modifier keys (`Alt`, `Control`, `Meta`, `Shift`) held down this way are reported on all
+    /// subsequent key and mouse events of this tab, e.g. for Shift+click. Pressing an already
+    /// held key again produces an auto-repeat event.
+    pub fn key_down(&self, key: &str) -> Result<&Self, Error> {
         let definition = keys::get_key_definition(key)?;
+        let (auto_repeat, modifiers) = {
+            let mut pressed_keys = self.pressed_keys.lock().unwrap();
+            let auto_repeat = pressed_keys.press(definition);
+            (auto_repeat, pressed_keys.modifiers())
+        };
 
         // See https://github.com/GoogleChrome/puppeteer/blob/62da2366c65b335751896afbb0206f23c61436f1/lib/Input.js#L114-L115
-        let text = definition.text.or_else(|| {
-            if definition.key.len() == 1 {
-                Some(definition.key)
-            } else {
-                None
-            }
-        });
+        // Chrome would insert the text of e.g. Ctrl+A into an input, so only keep it if Shift
+        // is the only modifier being held.
+        let text = if modifiers & !Modifier::Shift.bitmask() == 0 {
+            definition.text.or_else(|| {
+                if definition.key.len() == 1 {
+                    Some(definition.key)
+                } else {
+                    None
+                }
+            })
+        } else {
+            None
+        };
 
         // See https://github.com/GoogleChrome/puppeteer/blob/62da2366c65b335751896afbb0206f23c61436f1/lib/Input.js#L52
         let key_down_event_type = if text.is_some() {
@@ -298,53 +551,318 @@ impl Tab {
             "rawKeyDown"
         };
 
-        let key = Some(definition.key);
-        let code = Some(definition.code);
-
         self.call_method(input::methods::DispatchKeyEvent {
             event_type: key_down_event_type,
-            key,
+            key: Some(definition.key),
             text,
+            modifiers: Some(modifiers),
             code: Some(definition.code),
             windows_virtual_key_code: definition.key_code,
             native_virtual_key_code: definition.key_code,
+            auto_repeat: Some(auto_repeat),
+            is_keypad: Some(definition.location() == 3),
+            is_system_key: None,
+            location: Some(definition.location()),
         })?;
+        Ok(self)
+    }
+
+    /// Release a key previously pressed with `key_down`.
+    pub fn key_up(&self, key: &str) -> Result<&Self, Error> {
+        let definition = keys::get_key_definition(key)?;
+        let modifiers = {
+            let mut pressed_keys = self.pressed_keys.lock().unwrap();
+            pressed_keys.release(definition);
+            pressed_keys.modifiers()
+        };
+
         self.call_method(input::methods::DispatchKeyEvent {
             event_type: "keyUp",
-            key,
-            text,
-            code,
+            key: Some(definition.key),
+            text: None,
+            modifiers: Some(modifiers),
+            code: Some(definition.code),
             windows_virtual_key_code: definition.key_code,
             native_virtual_key_code: definition.key_code,
+            auto_repeat: None,
+            is_keypad: Some(definition.location() == 3),
+            is_system_key: None,
+            location: Some(definition.location()),
         })?;
         Ok(self)
     }
 
-    pub fn click_point(&self, point: Point) -> Result<&Self, Error> {
-        trace!("Clicking point: {:?}", point);
-        if point.x == 0.0 && point.y == 0.0 {
-            warn!("Midpoint of element shouldn't be 0,0. Something is probably wrong.")
+    /// Press a combination of keys such as `"Control+Shift+K"` or `"Shift+Tab"`.
+    ///
+    /// All keys are pressed in the given order and then released in reverse order. `Ctrl`,
+    /// `Cmd`/`Command` and `Option` can be used as aliases for `Control`, `Meta` and `Alt`.
+    ///
+    /// ```rust,no_run
+    /// # use failure::Error;
+    /// # fn main() -> Result<(), Error> {
+    /// #
+    /// # use headless_chrome::{Browser, LaunchOptionsBuilder};
+    /// # let browser = Browser::new(LaunchOptionsBuilder::default().build().unwrap())?;
+    /// # let tab = browser.wait_for_initial_tab()?;
+    /// tab.wait_for_element("input")?.click()?;
+    /// tab.press_chord("Control+a")?.press_key("Backspace")?;
+    /// #
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn press_chord(&self, chord: &str) -> Result<&Self, Error> {
+        let definitions = keys::parse_chord(chord)?;
+        for definition in &definitions {
+            self.key_down(definition.key)?;
+        }
+        for definition in definitions.iter().rev() {
+            self.key_up(definition.key)?;
+        }
+        Ok(self)
+    }
+
+    fn current_modifiers(&self) -> u32 {
+        self.pressed_keys.lock().unwrap().modifiers()
+    }
+
+    /// The last position the mouse was moved to by this tab.
+    ///
+    /// Chrome doesn't tell us where the mouse is, so this only reflects the events we
+    /// dispatched ourselves; it starts out at the top left corner of the viewport.
+    pub fn get_mouse_position(&self) -> Point {
+        *self.mouse_position.lock().unwrap()
+    }
+
+    /// Move the mouse to the given point in one step, without pressing any buttons.
+    pub fn move_mouse_to(&self, point: Point) -> Result<&Self, Error> {
+        self.move_mouse_in_steps(point, 1, MouseButton::None)
+    }
+
+    /// Move the mouse from its current position to `point`, dispatching `steps` evenly spaced
+    /// `mouseMoved` events along the way.
+    ///
+    /// `pressed` is reported as being held down during the movement, which is what pages
+    /// listening for drag gestures expect.
+    fn move_mouse_in_steps(
+        &self,
+        point: Point,
+        steps: u32,
+        pressed: MouseButton,
+    ) -> Result<&Self, Error> {
+        let start = self.get_mouse_position();
+        let steps = steps.max(1);
+
+        for step in 1..=steps {
+            let position = start + (point - start) * (f64::from(step) / f64::from(steps));
+            self.call_method(input::methods::DispatchMouseEvent {
+                event_type: "mouseMoved",
+                x: position.x,
+                y: position.y,
+                modifiers: Some(self.current_modifiers()),
+                button: Some(pressed),
+                buttons: Some(pressed.bitmask()),
+                ..Default::default()
+            })?;
+            *self.mouse_position.lock().unwrap() = position;
         }
 
+        Ok(self)
+    }
+
+    fn press_mouse_button(&self, button: MouseButton, click_count: u8) -> Result<&Self, Error> {
+        let position = self.get_mouse_position();
         self.call_method(input::methods::DispatchMouseEvent {
-            event_type: "mouseMoved",
-            x: point.x,
-            y: point.y,
+            event_type: "mousePressed",
+            x: position.x,
+            y: position.y,
+            modifiers: Some(self.current_modifiers()),
+            button: Some(button),
+            buttons: Some(button.bitmask()),
+            click_count: Some(click_count),
             ..Default::default()
         })?;
+        Ok(self)
+    }
+
+    fn release_mouse_button(&self, button: MouseButton, click_count: u8) -> Result<&Self, Error> {
+        let position = self.get_mouse_position();
         self.call_method(input::methods::DispatchMouseEvent {
-            event_type: "mousePressed",
-            x: point.x,
-            y: point.y,
-            button: Some("left"),
-            click_count: Some(1),
+            event_type: "mouseReleased",
+            x: position.x,
+            y: position.y,
+            modifiers: Some(self.current_modifiers()),
+            button: Some(button),
+            buttons: Some(0),
+            click_count: Some(click_count),
+            ..Default::default()
         })?;
+        Ok(self)
+    }
+
+    /// Move the mouse to `point` and click `click_count` times with the given button.
+    ///
+    /// Like a real double click, a `click_count` of 2 dispatches a single click first and
+    /// then one carrying a click count of 2.
+    pub fn click_point_with(
+        &self,
+        point: Point,
+        button: MouseButton,
+        click_count: u8,
+    ) -> Result<&Self, Error> {
+        trace!("Clicking point {:?} with {:?} button", point, button);
+        if point.x == 0.0 && point.y == 0.0 {
+            warn!("Midpoint of element shouldn't be 0,0. Something is probably wrong.")
+        }
+
+        self.move_mouse_to(point)?;
+        for count in 1..=click_count {
+            self.press_mouse_button(button, count)?;
+            self.release_mouse_button(button, count)?;
+        }
+        Ok(self)
+    }
+
+    /// Move the mouse to `point` and click it with the left button.
+    pub fn click_point(&self, point: Point) -> Result<&Self, Error> {
+        self.click_point_with(point, MouseButton::Left, 1)
+    }
+
+    /// Move the mouse to `point` and double click it, e.g. to select a word.
+    pub fn double_click_point(&self, point: Point) -> Result<&Self, Error> {
+        self.click_point_with(point, MouseButton::Left, 2)
+    }
+
+    /// Move the mouse to `point` and right click it, e.g. to open a `contextmenu`.
+    pub fn right_click_point(&self, point: Point) -> Result<&Self, Error> {
+        self.click_point_with(point, MouseButton::Right, 1)
+    }
+
+    /// Move the mouse to `point` and click it with the middle button (wheel).
+    pub fn middle_click_point(&self, point: Point) -> Result<&Self, Error> {
+        self.click_point_with(point, MouseButton::Middle, 1)
+    }
+
+    /// Drag with the left mouse button from `from` to `to`.
+    ///
+    /// The movement in between is split into `steps` intermediate `mouseMoved` events, as
+    /// many drag-and-drop libraries ignore a drag that jumps straight to its destination.
+    pub fn drag(&self, from: Point, to: Point, steps: u32) -> Result<&Self, Error> {
+        trace!("Dragging from {:?} to {:?} in {} steps", from, to, steps);
+        self.move_mouse_to(from)?;
+        self.press_mouse_button(MouseButton::Left, 1)?;
+        self.move_mouse_in_steps(to, steps, MouseButton::Left)?;
+        self.release_mouse_button(MouseButton::Left, 1)?;
+        Ok(self)
+    }
+
+    /// Dispatch a mouse wheel event at the current mouse position.
+    ///
+    /// `delta_x` and `delta_y` are in CSS pixels; positive values scroll right and down.
+    pub fn scroll_wheel(&self, delta_x: f64, delta_y: f64) -> Result<&Self, Error> {
+        let position = self.get_mouse_position();
         self.call_method(input::methods::DispatchMouseEvent {
-            event_type: "mouseReleased",
+            event_type: "mouseWheel",
+            x: position.x,
+            y: position.y,
+            modifiers: Some(self.current_modifiers()),
+            delta_x: Some(delta_x),
+            delta_y: Some(delta_y),
+            ..Default::default()
+        })?;
+        Ok(self)
+    }
+
+    /// Make the page believe it's running on a touch device (`ontouchstart`, `navigator.maxTouchPoints`
+    /// and the `pointer: coarse` media query), as mobile sites often check for that.
+    pub fn set_touch_emulation(&self, enabled: bool) -> Result<&Self, Error> {
+        self.call_method(emulation::methods::SetTouchEmulationEnabled {
+            enabled,
+            max_touch_points: if enabled {
+                Some(MAX_TOUCH_POINTS)
+            } else {
+                None
+            },
+        })?;
+        Ok(self)
+    }
+
+    fn dispatch_touch_event(&self, event_type: &str, points: &[Point]) -> Result<&Self, Error> {
+        let touch_points: Vec<input::TouchPoint> = points
+            .iter()
+            .zip(0..)
+            .map(|(point, id)| input::TouchPoint {
+                x: point.x,
+                y: point.y,
+                radius_x: None,
+                radius_y: None,
+                force: None,
+                id: Some(id),
+            })
+            .collect();
+        self.call_method(input::methods::DispatchTouchEvent {
+            event_type,
+            touch_points: &touch_points,
+            modifiers: Some(self.current_modifiers()),
+        })?;
+        Ok(self)
+    }
+
+    /// Put one finger per point on the screen.
+    pub fn touch_start(&self, points: &[Point]) -> Result<&Self, Error> {
+        self.dispatch_touch_event("touchStart", points)
+    }
+
+    /// Move the fingers put down by `touch_start` to new positions, in the same order.
+    pub fn touch_move(&self, points: &[Point]) -> Result<&Self, Error> {
+        self.dispatch_touch_event("touchMove", points)
+    }
+
+    /// Lift all fingers off the screen.
+    pub fn touch_end(&self) -> Result<&Self, Error> {
+        self.dispatch_touch_event("touchEnd", &[])
+    }
+
+    /// Tap the screen with one finger at the given point.
+    pub fn tap_point(&self, point: Point) -> Result<&Self, Error> {
+        trace!("Tapping point: {:?}", point);
+        self.call_method(input::methods::SynthesizeTapGesture {
             x: point.x,
             y: point.y,
-            button: Some("left"),
-            click_count: Some(1),
+            duration: None,
+            tap_count: None,
+            gesture_source_type: Some(input::GestureSourceType::Touch),
+        })?;
+        Ok(self)
+    }
+
+    /// Swipe one finger from `from` to `to`, scrolling the page (or whatever is under the finger)
+    /// the way a user on a phone would.
+    ///
+    /// Returns once the gesture, including any fling, has completed.
+    pub fn swipe(&self, from: Point, to: Point) -> Result<&Self, Error> {
+        trace!("Swiping from {:?} to {:?}", from, to);
+        let distance = to - from;
+        self.call_method(input::methods::SynthesizeScrollGesture {
+            x: from.x,
+            y: from.y,
+            x_distance: Some(distance.x),
+            y_distance: Some(distance.y),
+            gesture_source_type: Some(input::GestureSourceType::Touch),
+            ..Default::default()
+        })?;
+        Ok(self)
+    }
+
+    /// Pinch around `center` with two fingers, zooming in for a `scale` above 1.0 and out for
+    /// one below.
+    pub fn pinch(&self, center: Point, scale: f64) -> Result<&Self, Error> {
+        trace!("Pinching around {:?} by {}", center, scale);
+        self.call_method(input::methods::SynthesizePinchGesture {
+            x: center.x,
+            y: center.y,
+            scale_factor: scale,
+            relative_speed: None,
+            gesture_source_type: Some(input::GestureSourceType::Touch),
         })?;
         Ok(self)
     }
@@ -414,6 +932,24 @@ impl Tab {
         Ok(self)
     }
 
+    /// Clears all the browser's cookies (or those of the browser context the tab is in).
+    pub fn clear_browser_cookies(&self) -> Result<&Self, Error> {
+        self.call_method(network::methods::ClearBrowserCookies {})?;
+        Ok(self)
+    }
+
+    /// Closes the tab, returning whether the browser agreed to.
+    ///
+    /// Calls on the tab fail from then on.
+    pub fn close(&self) -> Result<bool, Error> {
+        Ok(self
+            .transport
+            .call_method_on_browser(target::methods::CloseTarget {
+                target_id: &self.target_id,
+            })?
+            .success)
+    }
+
     /// Enables the profiler
     pub fn enable_profiler(&self) -> Result<&Self, Error> {
         self.call_method(profiler::methods::Enable {})?;
@@ -470,4 +1006,297 @@ impl Tab {
             .result;
         Ok(script_coverages)
     }
+
+    /// Sets how often `start_cpu_profile` samples the stack, which DevTools has as 100µs by
+    /// default. Has to be called before starting the profile, and is counted in whole
+    /// microseconds.
+    ///
+    /// Will return error unless `enable_profiler` has been called, or if the interval is under
+    /// 1µs or doesn't fit in a `u32` of microseconds.
+    pub fn set_cpu_profile_sampling_interval(&self, interval: Duration) -> Result<&Self, Error> {
+        let interval = u32::try_from(interval.as_micros())
+            .ok()
+            .filter(|&interval| interval > 0)
+            .ok_or_else(|| format_err!("Invalid sampling interval: {:?}", interval))?;
+        self.call_method(profiler::methods::SetSamplingInterval { interval })?;
+        Ok(self)
+    }
+
+    /// Starts recording a CPU profile of the tab's JavaScript, like the record button in the
+    /// JavaScript Profiler panel of Chrome DevTools.
+    ///
+    /// Will return error unless `enable_profiler` has been called.
+    pub fn start_cpu_profile(&self) -> Result<&Self, Error> {
+        self.call_method(profiler::methods::Start {})?;
+        Ok(self)
+    }
+
+    /// Stops recording a CPU profile, returning it.
+    pub fn stop_cpu_profile(&self) -> Result<profiler::Profile, Error> {
+        Ok(self.call_method(profiler::methods::Stop {})?.profile)
+    }
+
+    /// Starts tracking which CSS rules the tab uses, like `start_js_coverage` does for
+    /// JavaScript.
+    ///
+    /// Enables the DOM and CSS domains, which the tracking needs.
+    pub fn start_css_coverage(&self) -> Result<&Self, Error> {
+        self.call_method(dom::methods::Enable {})?;
+        self.call_method(css::methods::Enable {})?;
+        self.call_method(css::methods::StartRuleUsageTracking {})?;
+        Ok(self)
+    }
+
+    /// Stops tracking which CSS rules the tab uses, returning which of the rules of each of its
+    /// style sheets were used since `start_css_coverage`, and disables the CSS domain again.
+    pub fn stop_css_coverage(&self) -> Result<Vec<StyleSheetCoverage>, Error> {
+        let coverage = self.css_coverage();
+        let disabled = self.call_method(css::methods::Disable {});
+        let coverage = coverage?;
+        disabled?;
+        Ok(coverage)
+    }
+
+    fn css_coverage(&self) -> Result<Vec<StyleSheetCoverage>, Error> {
+        let rule_usage = self
+            .call_method(css::methods::StopRuleUsageTracking {})?
+            .rule_usage;
+
+        let mut coverage: Vec<StyleSheetCoverage> = vec![];
+        for rule in rule_usage {
+            let index = match coverage
+                .iter()
+                .position(|style_sheet| style_sheet.style_sheet_id == rule.style_sheet_id)
+            {
+                Some(index) => index,
+                None => {
+                    coverage.push(StyleSheetCoverage {
+                        style_sheet_id: rule.style_sheet_id.clone(),
+                        ..StyleSheetCoverage::default()
+                    });
+                    coverage.len() - 1
+                }
+            };
+            let range = rule.start_offset as u32..rule.end_offset as u32;
+            if rule.used {
+                coverage[index].used.push(range);
+            } else {
+                coverage[index].unused.push(range);
+            }
+        }
+
+        let style_sheet_ids: Vec<&str> = coverage
+            .iter()
+            .map(|style_sheet| style_sheet.style_sheet_id.as_str())
+            .collect();
+        self.wait_to_hear_about(&[], &style_sheet_ids);
+        for style_sheet in &mut coverage {
+            if let Some(header) = self
+                .style_sheets
+                .lock()
+                .unwrap()
+                .get(&style_sheet.style_sheet_id)
+            {
+                style_sheet.url = header.source_url.clone();
+                if header.is_inline {
+                    style_sheet.start_line = header.start_line as u32;
+                    style_sheet.start_column = header.start_column as u32;
+                }
+            }
+            style_sheet.text = self
+                .call_method(css::methods::GetStyleSheetText {
+                    style_sheet_id: &style_sheet.style_sheet_id,
+                })?
+                .text;
+        }
+        Ok(coverage)
+    }
+
+    /// Enables the debugger, which `get_script_source` needs.
+    ///
+    /// Note that while it's enabled, `debugger` statements pause the page.
+    pub fn enable_debugger(&self) -> Result<&Self, Error> {
+        self.call_method(debugger::methods::Enable {})?;
+        self.debugger_enabled.store(true, Ordering::SeqCst);
+        Ok(self)
+    }
+
+    /// Disables the debugger
+    pub fn disable_debugger(&self) -> Result<&Self, Error> {
+        self.call_method(debugger::methods::Disable {})?;
+        self.debugger_enabled.store(false, Ordering::SeqCst);
+        Ok(self)
+    }
+
+    /// Whether `enable_debugger` has been called (since `disable_debugger` last was).
+    pub(crate) fn is_debugger_enabled(&self) -> bool {
+        self.debugger_enabled.load(Ordering::SeqCst)
+    }
+
+    /// The source of a script, e.g. one in the results of `take_precise_js_coverage`.
+    ///
+    /// Will return error unless `enable_debugger` has been called.
+    pub fn get_script_source(&self, script_id: &str) -> Result<String, Error> {
+        Ok(self
+            .call_method(debugger::methods::GetScriptSource { script_id })?
+            .script_source)
+    }
+
+    /// Waits up to a second for the tab to tell us about the scripts and style sheets with
+    /// these IDs, which it does in events that may still be on their way, for every one it has
+    /// while the debugger or CSS domain is enabled. Logs the ones it hasn't by then.
+    pub(crate) fn wait_to_hear_about(&self, script_ids: &[&str], style_sheet_ids: &[&str]) {
+        let missing = || {
+            let scripts = self.scripts.lock().unwrap();
+            let style_sheets = self.style_sheets.lock().unwrap();
+            let missing_scripts = script_ids.iter().filter(|id| !scripts.contains_key(**id));
+            let missing_style_sheets = style_sheet_ids
+                .iter()
+                .filter(|id| !style_sheets.contains_key(**id));
+            missing_scripts
+                .chain(missing_style_sheets)
+                .copied()
+                .collect::<Vec<_>>()
+        };
+        let waited = util::Wait::with_timeout(Duration::from_secs(1)).until(|| {
+            if missing().is_empty() {
+                Some(())
+            } else {
+                None
+            }
+        });
+        if waited.is_err() {
+            warn!(
+                "Gave up waiting to hear about scripts or style sheets {:?}",
+                missing()
+            );
+        }
+    }
+
+    /// The source map of a script, if it has one, from its `sourceMappingURL` comment or
+    /// `SourceMap` header. Maps are loaded once, with `SourceMap::load`, then cached, so the
+    /// first call for a script can block on fetching its map over HTTP.
+    ///
+    /// Only works for scripts the tab has parsed while the debugger was enabled (see
+    /// `enable_debugger`), which includes those it had when it was enabled.
+    pub fn get_source_map(&self, script_id: &str) -> Result<Option<Arc<SourceMap>>, Error> {
+        let script = match self.scripts.lock().unwrap().get(script_id) {
+            Some(script) => script.clone(),
+            None => return Ok(None),
+        };
+        let map_url = match script.source_map_url {
+            Some(map_url) if !map_url.is_empty() => map_url,
+            _ => return Ok(None),
+        };
+
+        let key = (script.url, map_url);
+        if let Some(source_map) = self.source_maps.lock().unwrap().get(&key) {
+            return Ok(Some(Arc::clone(source_map)));
+        }
+        debug!("Loading source map {} of {}", key.1, key.0);
+        let source_map = Arc::new(SourceMap::load(&key.0, &key.1)?);
+        self.source_maps
+            .lock()
+            .unwrap()
+            .insert(key, Arc::clone(&source_map));
+        Ok(Some(source_map))
+    }
+
+    /// Where the code at a line and column (both counting from 0) of a script came from,
+    /// according to its source map.
+    pub fn original_location(
+        &self,
+        script_id: &str,
+        line: u32,
+        column: u32,
+    ) -> Result<Option<OriginalLocation>, Error> {
+        Ok(self
+            .get_source_map(script_id)?
+            .and_then(|source_map| source_map.lookup(line, column)))
+    }
+
+    /// A copy of a stack trace (e.g. a `JsException`'s) with the URLs, lines and columns of
+    /// its frames mapped back to the original sources where the scripts have source maps,
+    /// which are loaded as in `get_source_map`. Frames whose maps can't be loaded are left as
+    /// they are.
+    pub fn original_stack_trace(&self, stack_trace: &StackTrace) -> StackTrace {
+        let mut stack_trace = stack_trace.clone();
+        let mut next = Some(&mut stack_trace);
+        while let Some(stack_trace) = next {
+            for frame in &mut stack_trace.call_frames {
+                let location = self.original_location(
+                    &frame.script_id,
+                    frame.line_number,
+                    frame.column_number,
+                );
+                match location {
+                    Ok(Some(location)) => {
+                        frame.url = location.source;
+                        frame.line_number = location.line;
+                        frame.column_number = location.column;
+                    }
+                    Ok(None) => {}
+                    Err(error) => warn!("Leaving frame in {} as it is: {}", frame.url, error),
+                }
+            }
+            next = stack_trace.parent.as_mut().map(Box::as_mut);
+        }
+        stack_trace
+    }
+
+    /// Enables the runtime domain, so the tab reports `Runtime.consoleAPICalled` and
+    /// `Runtime.exceptionThrown` events, e.g. to a `TransportHook`.
+    pub fn enable_runtime(&self) -> Result<&Self, Error> {
+        self.call_method(runtime::methods::Enable {})?;
+        Ok(self)
+    }
+
+    /// Starts recording a performance trace of the tab, as the Performance panel of Chrome
+    /// DevTools does, for `stop_tracing` to write out.
+    ///
+    /// Traces reported in events (`TransferMode::ReportEvents`) are kept in memory until
+    /// then, so `TransferMode::ReturnAsStream` is better for long ones.
+    pub fn start_tracing(
+        &self,
+        config: TraceConfig,
+        transfer_mode: TransferMode,
+    ) -> Result<&Self, Error> {
+        *self.trace.lock().unwrap() = Trace::default();
+        self.call_method(tracing::methods::Start {
+            trace_config: config,
+            transfer_mode,
+            stream_format: match transfer_mode {
+                TransferMode::ReturnAsStream => Some("json"),
+                TransferMode::ReportEvents => None,
+            },
+        })?;
+        Ok(self)
+    }
+
+    /// Stops recording a trace and writes it to `out` as JSON, which chrome://tracing,
+    /// Perfetto and the Performance panel of Chrome DevTools can all load.
+    ///
+    /// Waits as long as `set_default_timeout` says for the tab to finish collecting it.
+    pub fn stop_tracing<W: std::io::Write>(&self, mut out: W) -> Result<(), Error> {
+        self.call_method(tracing::methods::End {})?;
+        let trace = Arc::clone(&self.trace);
+        let complete = util::Wait::with_timeout(self.call_timeout())
+            .until(|| trace.lock().unwrap().complete.take())?;
+        if complete.data_loss_occurred {
+            warn!("Some of the trace was lost, as the browser's buffer filled up");
+        }
+
+        match complete.stream {
+            Some(handle) => {
+                std::io::copy(&mut IoStream::new(self, handle), &mut out)?;
+            }
+            None => {
+                let mut events = vec![];
+                std::mem::swap(&mut events, &mut self.trace.lock().unwrap().events);
+                serde_json::to_writer(&mut out, &serde_json::json!({ "traceEvents": events }))?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
 }
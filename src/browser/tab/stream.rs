@@ -0,0 +1,74 @@
+use std::io::Read;
+
+use log::*;
+
+use super::Tab;
+use crate::protocol::io;
+
+/// Reads a stream the browser has handed us the handle of, such as a trace (see
+/// `Tab::stop_tracing`) or a PDF printed with `transferMode: "ReturnAsStream"`, a chunk at a
+/// time with `IO.read`. The stream is closed when this is dropped.
+#[derive(Debug)]
+pub struct IoStream<'a> {
+    tab: &'a Tab,
+    handle: String,
+    chunk: Vec<u8>,
+    position: usize,
+    eof: bool,
+}
+
+impl<'a> IoStream<'a> {
+    pub fn new<S: Into<String>>(tab: &'a Tab, handle: S) -> Self {
+        Self {
+            tab,
+            handle: handle.into(),
+            chunk: vec![],
+            position: 0,
+            eof: false,
+        }
+    }
+
+    fn read_chunk(&mut self) -> Result<(), crate::Error> {
+        let chunk = self.tab.call_method(io::methods::Read {
+            handle: &self.handle,
+            offset: None,
+            size: None,
+        })?;
+        self.chunk = if chunk.base64_encoded.unwrap_or(false) {
+            base64::decode(&chunk.data)?
+        } else {
+            chunk.data.into_bytes()
+        };
+        self.position = 0;
+        self.eof = chunk.eof;
+        Ok(())
+    }
+}
+
+impl Read for IoStream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            // io::Error::other needs Rust 1.74
+            #[allow(clippy::io_other_error)]
+            self.read_chunk()
+                .map_err(|error| std::io::Error::new(std::io::ErrorKind::Other, error))?;
+        }
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+impl Drop for IoStream<'_> {
+    fn drop(&mut self) {
+        if let Err(error) = self.tab.call_method(io::methods::Close {
+            handle: &self.handle,
+        }) {
+            warn!("Couldn't close stream {}: {}", self.handle, error);
+        }
+    }
+}
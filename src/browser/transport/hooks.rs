@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::*;
+use serde_json::Value;
+
+use super::SessionId;
+use crate::protocol::{CallId, Event, Response};
+
+/// A method call on its way to the browser.
+#[derive(Debug, Clone)]
+pub struct CallInfo {
+    pub call_id: CallId,
+    pub method: &'static str,
+    /// The tab session the call was made on, or `None` for calls on the browser itself.
+    pub session_id: Option<SessionId>,
+    pub sent_at: Instant,
+}
+
+/// Observes the protocol traffic going through a `Transport`, e.g. to collect metrics, open
+/// tracing spans or log it (see `ProtocolLogger`).
+///
+/// Calls on tabs are reported as themselves, with their `session_id`, rather than as the
+/// `Target.sendMessageToTarget` calls which carry them. Hooks are called from whichever thread
+/// sends or receives the message, so should be quick, and must not make calls of their own.
+pub trait TransportHook: Send + Sync + std::fmt::Debug {
+    /// Called just before a method call is sent; `message` is what will be sent.
+    fn on_call(&self, _call: &CallInfo, _message: &Value) {}
+
+    /// Called when the browser answers a call, with how long it took to do so.
+    fn on_response(&self, _call: &CallInfo, _response: &Response, _latency: Duration) {}
+
+    /// Called instead of `on_response` for a call which won't be answered, because it timed
+    /// out, couldn't be sent, or the connection or tab went away first.
+    fn on_call_abandoned(&self, _call: &CallInfo) {}
+
+    /// Called for every event from the browser, or from the tab with the given session.
+    fn on_event(&self, _event: &Event, _session_id: Option<&SessionId>) {}
+}
+
+/// Logs all protocol traffic at debug level, pretty-printed and in full.
+#[derive(Debug, Default)]
+pub struct ProtocolLogger;
+
+impl TransportHook for ProtocolLogger {
+    fn on_call(&self, call: &CallInfo, message: &Value) {
+        debug!(
+            "--> #{} {} {}",
+            call.call_id,
+            describe_session(call.session_id.as_ref()),
+            pretty(message)
+        );
+    }
+
+    fn on_response(&self, call: &CallInfo, response: &Response, latency: Duration) {
+        let body = match (&response.result, &response.error) {
+            (_, Some(error)) => error.to_string(),
+            (Some(result), None) => pretty(result),
+            (None, None) => String::new(),
+        };
+        debug!(
+            "<-- #{} {} {} after {:?}: {}",
+            call.call_id,
+            call.method,
+            describe_session(call.session_id.as_ref()),
+            latency,
+            body
+        );
+    }
+
+    fn on_call_abandoned(&self, call: &CallInfo) {
+        debug!(
+            "--x #{} {} {} abandoned after {:?}",
+            call.call_id,
+            call.method,
+            describe_session(call.session_id.as_ref()),
+            call.sent_at.elapsed()
+        );
+    }
+
+    fn on_event(&self, event: &Event, session_id: Option<&SessionId>) {
+        debug!("<-- event {} {:#?}", describe_session(session_id), event);
+    }
+}
+
+fn describe_session(session_id: Option<&SessionId>) -> String {
+    match session_id {
+        Some(session_id) => format!("[{}]", session_id.as_str()),
+        None => "[browser]".to_string(),
+    }
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+}
+
+/// The hooks registered on a transport, along with the calls they're waiting on responses to.
+#[derive(Debug, Default)]
+pub(crate) struct Hooks {
+    hooks: Mutex<Vec<Arc<dyn TransportHook>>>,
+    in_flight: Mutex<HashMap<CallId, CallInfo>>,
+}
+
+impl Hooks {
+    pub fn add(&self, hook: Arc<dyn TransportHook>) {
+        self.hooks.lock().unwrap().push(hook);
+    }
+
+    /// A copy, so that hooks aren't called with the lock held.
+    fn all(&self) -> Vec<Arc<dyn TransportHook>> {
+        self.hooks.lock().unwrap().clone()
+    }
+
+    pub fn call_sent(&self, call: CallInfo, message: &Value) {
+        let hooks = self.all();
+        if hooks.is_empty() {
+            return;
+        }
+        for hook in &hooks {
+            hook.on_call(&call, message);
+        }
+        self.in_flight.lock().unwrap().insert(call.call_id, call);
+    }
+
+    pub fn call_abandoned(&self, call_id: CallId) {
+        let call = self.in_flight.lock().unwrap().remove(&call_id);
+        if let Some(call) = call {
+            for hook in &self.all() {
+                hook.on_call_abandoned(&call);
+            }
+        }
+    }
+
+    pub fn response_received(&self, response: &Response) {
+        let call = self.in_flight.lock().unwrap().remove(&response.call_id);
+        if let Some(call) = call {
+            let latency = call.sent_at.elapsed();
+            for hook in &self.all() {
+                hook.on_response(&call, response, latency);
+            }
+        }
+    }
+
+    pub fn event_received(&self, event: &Event, session_id: Option<&SessionId>) {
+        for hook in &self.all() {
+            hook.on_event(event, session_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::browser::transport::{MockBrowser, Transport};
+    use crate::protocol::page::methods::Navigate;
+    use crate::protocol::target::methods::{AttachToTarget, SetDiscoverTargets};
+
+    #[derive(Debug, Default)]
+    struct Collector {
+        seen: Mutex<Vec<String>>,
+    }
+
+    impl TransportHook for Collector {
+        fn on_call(&self, call: &CallInfo, message: &Value) {
+            assert_eq!(call.method, message["method"]);
+            self.seen
+                .lock()
+                .unwrap()
+                .push(format!("call {}", call.method));
+        }
+
+        fn on_response(&self, call: &CallInfo, response: &Response, _latency: Duration) {
+            assert_eq!(call.call_id, response.call_id);
+            let outcome = if response.error.is_some() {
+                "error"
+            } else {
+                "ok"
+            };
+            self.seen
+                .lock()
+                .unwrap()
+                .push(format!("{} {}", outcome, call.method));
+        }
+
+        fn on_call_abandoned(&self, call: &CallInfo) {
+            self.seen
+                .lock()
+                .unwrap()
+                .push(format!("abandoned {}", call.method));
+        }
+
+        fn on_event(&self, event: &Event, session_id: Option<&SessionId>) {
+            let event_name = match event {
+                Event::TargetCreated(_) => "targetCreated",
+                Event::Lifecycle(_) => "lifecycleEvent",
+                _ => "other",
+            };
+            let on_tab = session_id.is_some();
+            self.seen
+                .lock()
+                .unwrap()
+                .push(format!("event {} on tab: {}", event_name, on_tab));
+        }
+    }
+
+    #[test]
+    fn hooks_see_calls_responses_and_events() {
+        env_logger::try_init().unwrap_or(());
+
+        let mock = MockBrowser::new();
+        mock.respond("Page.navigate", json!({ "frameId": "MAIN_FRAME" }))
+            .emit_after(
+                "Page.navigate",
+                json!({
+                    "method": "Page.lifecycleEvent",
+                    "params": {
+                        "frameId": "MAIN_FRAME",
+                        "loaderId": "LOADER",
+                        "name": "init",
+                        "timestamp": 1.0
+                    }
+                }),
+            );
+        let transport = Transport::with_connection(
+            |messages_tx| Ok(mock.connect(messages_tx)),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        let collector = Arc::new(Collector::default());
+        transport.add_hook(Arc::clone(&collector) as Arc<dyn TransportHook>);
+        transport.add_hook(Arc::new(ProtocolLogger));
+
+        transport
+            .call_method_on_browser(SetDiscoverTargets { discover: true })
+            .unwrap();
+        let session_id = transport
+            .call_method_on_browser(AttachToTarget {
+                target_id: "MOCK_TARGET_1",
+                flatten: None,
+            })
+            .unwrap()
+            .session_id;
+        transport
+            .call_method_on_target(
+                session_id.into(),
+                Navigate {
+                    url: "http://example.com",
+                },
+            )
+            .unwrap();
+        transport
+            .call_method_on_browser(crate::protocol::browser::methods::GetVersion {})
+            .unwrap_err();
+
+        // events are handled on another thread, so might come after the next call
+        crate::util::Wait::with_timeout(Duration::from_secs(1))
+            .until(|| {
+                if collector.seen.lock().unwrap().len() == 10 {
+                    Some(())
+                } else {
+                    None
+                }
+            })
+            .unwrap();
+        let (events, calls): (Vec<_>, Vec<_>) = collector
+            .seen
+            .lock()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .partition(|seen| seen.starts_with("event"));
+        assert_eq!(
+            vec![
+                "call Target.setDiscoverTargets",
+                "ok Target.setDiscoverTargets",
+                "call Target.attachToTarget",
+                "ok Target.attachToTarget",
+                "call Page.navigate",
+                "ok Page.navigate",
+                "call Browser.getVersion",
+                "error Browser.getVersion",
+            ],
+            calls
+        );
+        assert_eq!(
+            vec![
+                "event targetCreated on tab: false",
+                "event lifecycleEvent on tab: true",
+            ],
+            events
+        );
+    }
+
+    #[test]
+    fn forgets_calls_cut_off_by_disconnects() {
+        env_logger::try_init().unwrap_or(());
+
+        let mock = MockBrowser::new();
+        mock.never_respond("Browser.getVersion");
+        let transport = Transport::with_connection(
+            |messages_tx| Ok(mock.connect(messages_tx)),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        let collector = Arc::new(Collector::default());
+        transport.add_hook(Arc::clone(&collector) as Arc<dyn TransportHook>);
+        transport.add_hook(Arc::new(ProtocolLogger));
+
+        let closing_mock = mock.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            closing_mock.close_connections();
+        });
+        transport
+            .call_method_on_browser(crate::protocol::browser::methods::GetVersion {})
+            .unwrap_err();
+        assert!(transport.hooks.in_flight.lock().unwrap().is_empty());
+        assert_eq!(
+            vec!["call Browser.getVersion", "abandoned Browser.getVersion"],
+            *collector.seen.lock().unwrap()
+        );
+    }
+}
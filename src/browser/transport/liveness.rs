@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::process::ExitStatus;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::*;
+
+use super::SessionId;
+use crate::util;
+
+/// Why the connection to a browser ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisconnectReason {
+    /// The connection was closed while, as far as we know, the browser was still running.
+    ConnectionClosed,
+    /// The browser process exited, with the given code unless it was killed by a signal.
+    BrowserExited(Option<i32>),
+    /// The browser didn't send us anything for longer than the idle browser timeout.
+    Idle,
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisconnectReason::ConnectionClosed => write!(f, "the connection was closed"),
+            DisconnectReason::BrowserExited(Some(code)) => {
+                write!(f, "the browser exited with code {}", code)
+            }
+            DisconnectReason::BrowserExited(None) => write!(f, "the browser was killed"),
+            DisconnectReason::Idle => write!(f, "the browser went quiet for too long"),
+        }
+    }
+}
+
+type ExitProbe = Box<dyn Fn() -> Option<ExitStatus> + Send>;
+type DisconnectCallback = Box<dyn Fn(&DisconnectReason) + Send>;
+
+/// Whether the connection, and each of the targets we talk to over it, are still alive.
+#[derive(Default)]
+pub(crate) struct Liveness {
+    disconnect_reason: Mutex<Option<DisconnectReason>>,
+    exit_probe: Mutex<Option<ExitProbe>>,
+    callbacks: Mutex<Vec<DisconnectCallback>>,
+    crashed_sessions: Mutex<HashSet<SessionId>>,
+}
+
+impl fmt::Debug for Liveness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Liveness {{ disconnect_reason: {:?} }}",
+            self.disconnect_reason()
+        )
+    }
+}
+
+impl Liveness {
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        self.disconnect_reason.lock().unwrap().clone()
+    }
+
+    pub fn set_exit_probe(&self, probe: ExitProbe) {
+        *self.exit_probe.lock().unwrap() = Some(probe);
+    }
+
+    /// Calls `callback` once disconnected, or right away if that's already happened.
+    pub fn on_disconnect(&self, callback: DisconnectCallback) {
+        let reason = {
+            // held while checking, so `notify_disconnected` can't drain the callbacks between
+            // that and us adding this one
+            let mut callbacks = self.callbacks.lock().unwrap();
+            match self.disconnect_reason() {
+                Some(reason) => reason,
+                None => {
+                    callbacks.push(callback);
+                    return;
+                }
+            }
+        };
+        // not held while calling, in case the callback adds callbacks of its own
+        callback(&reason);
+    }
+
+    /// How the browser exited, if it has. Gives it a moment to do so, as connections tend to
+    /// close just before the process can be reaped.
+    pub fn browser_exit(&self) -> Option<DisconnectReason> {
+        let exit_probe = self.exit_probe.lock().unwrap();
+        let exit_probe = exit_probe.as_ref()?;
+        util::Wait::new(Duration::from_millis(500), Duration::from_millis(20))
+            .until(exit_probe)
+            .ok()
+            .map(|status| DisconnectReason::BrowserExited(status.code()))
+    }
+
+    pub fn disconnected(&self, reason: DisconnectReason) {
+        warn!("Disconnected from browser: {}", reason);
+        *self.disconnect_reason.lock().unwrap() = Some(reason);
+    }
+
+    /// Tells everyone waiting to hear about it that we've been `disconnected`.
+    pub fn notify_disconnected(&self) {
+        let (reason, callbacks) = {
+            let mut callbacks = self.callbacks.lock().unwrap();
+            match self.disconnect_reason() {
+                Some(reason) => (reason, callbacks.drain(..).collect::<Vec<_>>()),
+                None => return,
+            }
+        };
+        for callback in callbacks {
+            callback(&reason);
+        }
+    }
+
+    pub fn target_crashed(&self, session_id: SessionId) {
+        warn!("Target of session {:?} crashed", session_id);
+        self.crashed_sessions.lock().unwrap().insert(session_id);
+    }
+
+    pub fn is_crashed(&self, session_id: &SessionId) -> bool {
+        self.crashed_sessions.lock().unwrap().contains(session_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{mpsc, Arc};
+
+    use super::*;
+
+    #[test]
+    fn callbacks_can_add_callbacks() {
+        let liveness = Arc::new(Liveness::default());
+        let (tx, rx) = mpsc::channel();
+
+        let inner_liveness = Arc::clone(&liveness);
+        let inner_tx = tx.clone();
+        liveness.on_disconnect(Box::new(move |_| {
+            let inner_tx = inner_tx.clone();
+            inner_liveness.on_disconnect(Box::new(move |reason| {
+                inner_tx.send(reason.clone()).unwrap();
+            }));
+        }));
+        liveness.disconnected(DisconnectReason::ConnectionClosed);
+        liveness.notify_disconnected();
+
+        // and once already disconnected
+        let inner_liveness = Arc::clone(&liveness);
+        liveness.on_disconnect(Box::new(move |_| {
+            let tx = tx.clone();
+            inner_liveness.on_disconnect(Box::new(move |reason| {
+                tx.send(reason.clone()).unwrap();
+            }));
+        }));
+
+        assert_eq!(
+            vec![DisconnectReason::ConnectionClosed; 2],
+            rx.try_iter().collect::<Vec<_>>()
+        );
+    }
+}
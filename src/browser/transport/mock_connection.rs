@@ -0,0 +1,488 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+use failure::{Error, Fail};
+use log::*;
+use serde_json::{json, Value};
+
+use super::Connection;
+use crate::protocol::{self, RemoteError};
+
+/// What Chrome answers with when asked to call a method it doesn't know about.
+const METHOD_NOT_FOUND: i32 = -32601;
+
+#[derive(Debug, Fail)]
+#[fail(display = "The mock browser connection has been shut down")]
+pub struct MockConnectionClosed {}
+
+/// A method call received by a `MockBrowser`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockCall {
+    pub method: String,
+    pub params: Value,
+    /// The session the call was sent to, or `None` for calls on the browser itself.
+    pub session_id: Option<String>,
+}
+
+/// An in-process stand-in for Chrome which answers method calls from a script, so that code
+/// driving a `Browser` and its tabs can be tested without a browser binary.
+///
+/// Responses and events are keyed on method name, and apply to calls on the browser and on
+/// tabs alike. Events emitted after a call on a tab are delivered to that tab, unless they're
+/// about targets. Calls which haven't been scripted fail the way Chrome fails for unknown
+/// methods, except for the handful of `Target` and `Page` methods which `Browser` and `Tab` need
+/// to get going: the mock starts out with a single blank page, opens another for every
+/// `Target.createTarget`, and can close them and create and dispose of browser contexts.
+///
+/// Clones share the same script and call log, so keep one around to inspect `calls` after
+/// handing a connection to a `Browser`.
+///
+/// ```rust
+/// # use failure::Error;
+/// # fn main() -> Result<(), Error> {
+/// #
+/// use headless_chrome::Browser;
+/// use headless_chrome::browser::transport::MockBrowser;
+/// use serde_json::json;
+///
+/// let mock = MockBrowser::new();
+/// mock.respond("Page.navigate", json!({ "frameId": "MAIN_FRAME" }));
+///
+/// let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+/// let tab = browser.wait_for_initial_tab()?;
+/// tab.navigate_to("https://example.com")?;
+///
+/// assert!(mock.calls().iter().any(|call| call.method == "Page.navigate"));
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockBrowser {
+    script: Arc<Mutex<Script>>,
+}
+
+#[derive(Debug)]
+struct Script {
+    responses: HashMap<String, Result<Value, RemoteError>>,
+    events: HashMap<String, Vec<Value>>,
+    unanswered: HashSet<String>,
+    calls: Vec<MockCall>,
+    connections: Vec<mpsc::Sender<protocol::Message>>,
+    /// The open targets, along with the browser context each is in.
+    targets: Vec<(String, Option<String>)>,
+    targets_created: usize,
+    contexts_created: usize,
+    sessions: HashMap<String, String>,
+}
+
+impl Default for MockBrowser {
+    fn default() -> Self {
+        let script = Script {
+            responses: HashMap::new(),
+            events: HashMap::new(),
+            unanswered: HashSet::new(),
+            calls: vec![],
+            connections: vec![],
+            targets: vec![("MOCK_TARGET_1".to_string(), None)],
+            targets_created: 1,
+            contexts_created: 0,
+            sessions: HashMap::new(),
+        };
+        Self {
+            script: Arc::new(Mutex::new(script)),
+        }
+    }
+}
+
+impl MockBrowser {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Answer every call of `method` with the given `result` object.
+    pub fn respond(&self, method: &str, result: Value) -> &Self {
+        let mut script = self.script.lock().unwrap();
+        script.responses.insert(method.to_string(), Ok(result));
+        self
+    }
+
+    /// Answer every call of `method` with a protocol error.
+    pub fn respond_with_error(&self, method: &str, code: i32, message: &str) -> &Self {
+        let error = RemoteError {
+            code,
+            message: message.to_string(),
+        };
+        let mut script = self.script.lock().unwrap();
+        script.responses.insert(method.to_string(), Err(error));
+        self
+    }
+
+    /// Leave calls of `method` hanging, as a browser which is stuck would.
+    ///
+    /// For `Target.sendMessageToTarget`, that's every call on a tab, which doesn't even get as
+    /// far as the tab.
+    pub fn never_respond(&self, method: &str) -> &Self {
+        let mut script = self.script.lock().unwrap();
+        script.unanswered.insert(method.to_string());
+        self
+    }
+
+    /// After answering a call of `method`, send the given event, e.g.
+    /// `json!({ "method": "Page.frameStoppedLoading", "params": { "frameId": "MAIN_FRAME" } })`.
+    ///
+    /// Events are sent in the order they were added, on the session the call was made on, except
+    /// for those in the `Target` domain, which go to the browser.
+    pub fn emit_after(&self, method: &str, event: Value) -> &Self {
+        let mut script = self.script.lock().unwrap();
+        script
+            .events
+            .entry(method.to_string())
+            .or_default()
+            .push(event);
+        self
+    }
+
+    /// Close all connections to the mock, as a browser which crashed would.
+    pub fn close_connections(&self) {
+        let mut script = self.script.lock().unwrap();
+        for messages_tx in script.connections.drain(..) {
+            if messages_tx
+                .send(protocol::Message::ConnectionShutdown)
+                .is_err()
+            {
+                trace!("Transport loop had already stopped");
+            }
+        }
+    }
+
+    /// All method calls received so far, oldest first.
+    ///
+    /// Calls on tabs are listed as themselves rather than as the `Target.sendMessageToTarget`
+    /// calls which carry them.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.script.lock().unwrap().calls.clone()
+    }
+
+    /// Create a connection which plays back this script, for use with
+    /// `Browser::with_connection`.
+    pub fn connect(&self, messages_tx: mpsc::Sender<protocol::Message>) -> MockConnection {
+        let mut script = self.script.lock().unwrap();
+        script.connections.push(messages_tx.clone());
+        MockConnection {
+            script: Arc::clone(&self.script),
+            messages_tx: Mutex::new(Some(messages_tx)),
+        }
+    }
+}
+
+impl Script {
+    fn answer(
+        &mut self,
+        method: &str,
+        params: &Value,
+        session_id: Option<&str>,
+    ) -> Option<(Result<Value, RemoteError>, Vec<Value>)> {
+        self.calls.push(MockCall {
+            method: method.to_string(),
+            params: params.clone(),
+            session_id: session_id.map(ToString::to_string),
+        });
+        if self.unanswered.contains(method) {
+            return None;
+        }
+
+        let mut events = vec![];
+        let result = if let Some(result) = self.responses.get(method) {
+            result.clone()
+        } else {
+            match method {
+                "Target.setDiscoverTargets" => {
+                    events.extend(
+                        self.targets
+                            .iter()
+                            .map(|(target_id, _)| target_created(target_id, "about:blank")),
+                    );
+                    Ok(json!({}))
+                }
+                "Target.createTarget" => {
+                    self.targets_created += 1;
+                    let target_id = format!("MOCK_TARGET_{}", self.targets_created);
+                    let url = params["url"].as_str().unwrap_or("about:blank");
+                    let context_id = params["browserContextId"].as_str().map(ToString::to_string);
+                    events.push(target_created(&target_id, url));
+                    self.targets.push((target_id.clone(), context_id));
+                    Ok(json!({ "targetId": target_id }))
+                }
+                "Target.closeTarget" => {
+                    let target_id = params["targetId"].as_str().unwrap_or_default();
+                    let open_before = self.targets.len();
+                    self.targets.retain(|(id, _)| id != target_id);
+                    let success = self.targets.len() < open_before;
+                    if success {
+                        events.push(target_destroyed(target_id));
+                    }
+                    Ok(json!({ "success": success }))
+                }
+                "Target.createBrowserContext" => {
+                    self.contexts_created += 1;
+                    Ok(
+                        json!({ "browserContextId": format!("MOCK_CONTEXT_{}", self.contexts_created) }),
+                    )
+                }
+                "Target.disposeBrowserContext" => {
+                    let context_id = params["browserContextId"].as_str();
+                    let (disposed, kept) = self
+                        .targets
+                        .drain(..)
+                        .partition(|(_, context)| *context == context_id.map(str::to_string));
+                    self.targets = kept;
+                    let disposed: Vec<_> = disposed;
+                    events.extend(
+                        disposed
+                            .iter()
+                            .map(|(target_id, _)| target_destroyed(target_id)),
+                    );
+                    Ok(json!({}))
+                }
+                "Target.attachToTarget" => {
+                    let target_id = params["targetId"].as_str().unwrap_or_default();
+                    let session_id = format!("MOCK_SESSION_{}", self.sessions.len() + 1);
+                    self.sessions
+                        .insert(session_id.clone(), target_id.to_string());
+                    Ok(json!({ "sessionId": session_id }))
+                }
+                "Page.enable" | "Page.setLifecycleEventsEnabled" | "Inspector.enable" => {
+                    Ok(json!({}))
+                }
+                _ => Err(RemoteError {
+                    code: METHOD_NOT_FOUND,
+                    message: format!("'{}' wasn't found", method),
+                }),
+            }
+        };
+
+        events.extend(self.events.get(method).cloned().unwrap_or_default());
+        Some((result, events))
+    }
+}
+
+fn target_created(target_id: &str, url: &str) -> Value {
+    json!({
+        "method": "Target.targetCreated",
+        "params": {
+            "targetInfo": {
+                "targetId": target_id,
+                "type": "page",
+                "title": "",
+                "url": url,
+                "attached": false
+            }
+        }
+    })
+}
+
+fn target_destroyed(target_id: &str) -> Value {
+    json!({
+        "method": "Target.targetDestroyed",
+        "params": { "targetId": target_id }
+    })
+}
+
+/// The `Connection` end of a `MockBrowser`.
+#[derive(Debug)]
+pub struct MockConnection {
+    script: Arc<Mutex<Script>>,
+    messages_tx: Mutex<Option<mpsc::Sender<protocol::Message>>>,
+}
+
+impl MockConnection {
+    fn handle_call(&self, message_text: &str, session_id: Option<&str>) -> Result<(), Error> {
+        let call: Value = serde_json::from_str(message_text)?;
+        let call_id = &call["id"];
+        let method = call["method"].as_str().unwrap_or_default();
+        let params = &call["params"];
+
+        if session_id.is_none() && method == "Target.sendMessageToTarget" {
+            if self.script.lock().unwrap().unanswered.contains(method) {
+                trace!("Leaving call of {} unanswered", method);
+                return Ok(());
+            }
+            self.send(json!({ "id": call_id, "result": {} }), None)?;
+            let session_id = params["sessionId"].as_str().unwrap_or_default();
+            let message_text = params["message"].as_str().unwrap_or_default();
+            return self.handle_call(message_text, Some(session_id));
+        }
+
+        let answer = self
+            .script
+            .lock()
+            .unwrap()
+            .answer(method, params, session_id);
+        let (result, events) = match answer {
+            Some(answer) => answer,
+            None => {
+                trace!("Leaving call of {} unanswered", method);
+                return Ok(());
+            }
+        };
+
+        let response = match result {
+            Ok(result) => json!({ "id": call_id, "result": result }),
+            Err(error) => json!({
+                "id": call_id,
+                "error": { "code": error.code, "message": error.message }
+            }),
+        };
+        self.send(response, session_id)?;
+        for event in events {
+            // like Chrome, send events about targets to the browser rather than the tab
+            let is_target_event = event["method"]
+                .as_str()
+                .unwrap_or_default()
+                .starts_with("Target.");
+            self.send(event, if is_target_event { None } else { session_id })?;
+        }
+        Ok(())
+    }
+
+    fn send(&self, message: Value, session_id: Option<&str>) -> Result<(), Error> {
+        let message = match session_id {
+            Some(session_id) => {
+                let target_id = self
+                    .script
+                    .lock()
+                    .unwrap()
+                    .sessions
+                    .get(session_id)
+                    .cloned()
+                    .unwrap_or_default();
+                json!({
+                    "method": "Target.receivedMessageFromTarget",
+                    "params": {
+                        "sessionId": session_id,
+                        "targetId": target_id,
+                        "message": message.to_string()
+                    }
+                })
+            }
+            None => message,
+        };
+
+        let message: protocol::Message = serde_json::from_value(message)?;
+        let messages_tx = self.messages_tx.lock().unwrap();
+        messages_tx
+            .as_ref()
+            .ok_or(MockConnectionClosed {})?
+            .send(message)?;
+        Ok(())
+    }
+}
+
+impl Connection for MockConnection {
+    fn send_message(&self, message_text: &str) -> Result<(), Error> {
+        trace!("Mock browser received: {}", message_text);
+        self.handle_call(message_text, None)
+    }
+
+    fn shutdown(&self) {
+        trace!("Shutting down mock connection");
+        if let Some(messages_tx) = self.messages_tx.lock().unwrap().take() {
+            if messages_tx
+                .send(protocol::Message::ConnectionShutdown)
+                .is_err()
+            {
+                trace!("Transport loop had already stopped");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::browser::transport::{SessionId, Transport};
+    use crate::protocol::page::methods::Navigate;
+    use crate::protocol::target::methods::{AttachToTarget, SetDiscoverTargets};
+    use crate::protocol::Event;
+
+    #[test]
+    fn answers_browser_and_target_calls_from_script() {
+        env_logger::try_init().unwrap_or(());
+
+        let mock = MockBrowser::new();
+        mock.respond("Page.navigate", json!({ "frameId": "MAIN_FRAME" }))
+            .emit_after(
+                "Page.navigate",
+                json!({
+                    "method": "Page.lifecycleEvent",
+                    "params": {
+                        "frameId": "MAIN_FRAME",
+                        "loaderId": "LOADER",
+                        "name": "init",
+                        "timestamp": 1.0
+                    }
+                }),
+            )
+            .respond_with_error("DOM.getDocument", -32000, "No document");
+
+        let transport = Transport::with_connection(
+            |messages_tx| Ok(mock.connect(messages_tx)),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+
+        transport
+            .call_method_on_browser(SetDiscoverTargets { discover: true })
+            .unwrap();
+        let session_id: SessionId = transport
+            .call_method_on_browser(AttachToTarget {
+                target_id: "MOCK_TARGET_1",
+                flatten: None,
+            })
+            .unwrap()
+            .session_id
+            .into();
+        let events_rx = transport.listen_to_target_events(session_id.clone());
+
+        let navigated = transport
+            .call_method_on_target(
+                session_id.clone(),
+                Navigate {
+                    url: "http://example.com",
+                },
+            )
+            .unwrap();
+        assert_eq!("MAIN_FRAME", navigated.frame_id);
+        match events_rx.recv_timeout(Duration::from_secs(1)).unwrap() {
+            Event::Lifecycle(event) => assert_eq!("init", event.params.name),
+            event => panic!("Unexpected event: {:?}", event),
+        }
+
+        let document = transport.call_method_on_target(
+            session_id,
+            crate::protocol::dom::methods::GetDocument {
+                depth: None,
+                pierce: None,
+            },
+        );
+        assert_eq!(
+            "Method call error -32000: No document",
+            document.unwrap_err().to_string()
+        );
+
+        let methods: Vec<_> = mock.calls().into_iter().map(|call| call.method).collect();
+        assert_eq!(
+            vec![
+                "Target.setDiscoverTargets",
+                "Target.attachToTarget",
+                "Page.navigate",
+                "DOM.getDocument"
+            ],
+            methods
+        );
+    }
+}
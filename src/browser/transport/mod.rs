@@ -1,29 +1,60 @@
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc;
 use std::sync::mpsc::Receiver;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Instant;
 
 use failure::{Error, Fail};
 use log::*;
 
 use serde;
 
+use crate::protocol;
 use crate::protocol::target;
 use crate::protocol::Event;
 use crate::protocol::Message;
-use crate::{protocol, util};
 
 use crate::protocol::CallId;
+use hooks::Hooks;
+use liveness::Liveness;
 use std::time::Duration;
 use waiting_call_registry::WaitingCallRegistry;
 use web_socket_connection::WebSocketConnection;
 
+pub use hooks::{CallInfo, ProtocolLogger, TransportHook};
+pub use liveness::DisconnectReason;
+pub use mock_connection::{MockBrowser, MockCall, MockConnection};
+pub use pipe_connection::PipeConnection;
+pub use recording::{
+    Direction, RecordedMessage, ReplayConnection, ReplayMismatch, SessionRecorder,
+};
+
+mod hooks;
+mod liveness;
+mod mock_connection;
+mod pipe_connection;
+mod recording;
 mod waiting_call_registry;
 mod web_socket_connection;
 
+/// A channel to the browser over which raw protocol messages are exchanged, such as a
+/// WebSocket or a pair of pipes.
+///
+/// Implementations forward every message they receive from the browser to the
+/// `Sender<protocol::Message>` they were created with, and send a
+/// `Message::ConnectionShutdown` once the channel is closed.
+pub trait Connection: Send + Sync + std::fmt::Debug {
+    /// Send a single serialized method call to the browser.
+    fn send_message(&self, message_text: &str) -> Result<(), Error>;
+
+    /// Close the channel, so that the thread receiving messages stops.
+    fn shutdown(&self);
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SessionId(String);
 
@@ -33,7 +64,7 @@ pub enum MethodDestination {
 }
 
 impl SessionId {
-    fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &str {
         &self.0
     }
 }
@@ -54,51 +85,160 @@ type Listeners = Arc<Mutex<HashMap<ListenerId, Sender<Event>>>>;
 
 #[derive(Debug)]
 pub struct Transport {
-    web_socket_connection: Arc<WebSocketConnection>,
+    connection: Arc<dyn Connection>,
     waiting_call_registry: Arc<WaitingCallRegistry>,
     listeners: Listeners,
-    open: Arc<AtomicBool>,
+    liveness: Arc<Liveness>,
     call_id_counter: Arc<AtomicUsize>,
+    hooks: Arc<Hooks>,
+    call_timeout: Mutex<Duration>,
 }
 
+/// How long method calls wait for a response unless told otherwise.
+pub const DEFAULT_CALL_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Fail)]
-#[fail(display = "Unable to make method calls because underlying connection is closed")]
-pub struct ConnectionClosed {}
+#[fail(
+    display = "Unable to make method calls because underlying connection is closed: {}",
+    reason
+)]
+pub struct ConnectionClosed {
+    pub reason: DisconnectReason,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "The tab crashed")]
+pub struct TargetCrashed {}
+
+#[derive(Debug, Fail)]
+#[fail(display = "Method call {} timed out after {:?}", method, timeout)]
+pub struct MethodTimeout {
+    pub method: &'static str,
+    pub timeout: Duration,
+}
 
 impl Transport {
-    pub fn new(ws_url: String) -> Result<Self, Error> {
+    /// Connect to the browser over its WebSocket debugging URL, recording the messages
+    /// exchanged if given a `recorder`.
+    pub fn new(
+        ws_url: String,
+        idle_browser_timeout: Duration,
+        recorder: Option<Arc<SessionRecorder>>,
+    ) -> Result<Self, Error> {
+        Self::with_connection(
+            |messages_tx| WebSocketConnection::new(&ws_url, messages_tx, recorder),
+            idle_browser_timeout,
+        )
+    }
+
+    /// Talk to a browser launched with `--remote-debugging-pipe`, given our ends of the pipes
+    /// it reads from (fd 3) and writes to (fd 4).
+    pub fn over_pipe(
+        to_browser: std::fs::File,
+        from_browser: std::fs::File,
+        idle_browser_timeout: Duration,
+        recorder: Option<Arc<SessionRecorder>>,
+    ) -> Result<Self, Error> {
+        Self::with_connection(
+            |messages_tx| {
+                Ok(PipeConnection::new(
+                    to_browser,
+                    from_browser,
+                    messages_tx,
+                    recorder,
+                ))
+            },
+            idle_browser_timeout,
+        )
+    }
+
+    /// Talk to the browser over any `Connection`, such as a `MockConnection`. `connect` is
+    /// given the channel the connection should pass incoming messages on to.
+    pub fn with_connection<C, F>(connect: F, idle_browser_timeout: Duration) -> Result<Self, Error>
+    where
+        C: Connection + 'static,
+        F: FnOnce(mpsc::Sender<protocol::Message>) -> Result<C, Error>,
+    {
         let (messages_tx, messages_rx) = mpsc::channel();
-        let web_socket_connection = Arc::new(WebSocketConnection::new(&ws_url, messages_tx)?);
+        let connection: Arc<dyn Connection> = Arc::new(connect(messages_tx)?);
 
         let waiting_call_registry = Arc::new(WaitingCallRegistry::new());
 
         let listeners = Arc::new(Mutex::new(HashMap::new()));
 
-        let open = Arc::new(AtomicBool::new(true));
+        let liveness = Arc::new(Liveness::default());
+
+        let hooks = Arc::new(Hooks::default());
 
         Self::handle_incoming_messages(
             messages_rx,
             Arc::clone(&waiting_call_registry),
             Arc::clone(&listeners),
-            Arc::clone(&open),
-            Arc::clone(&web_socket_connection),
+            Arc::clone(&liveness),
+            Arc::clone(&connection),
+            Arc::clone(&hooks),
+            idle_browser_timeout,
         );
 
         Ok(Self {
-            web_socket_connection,
+            connection,
             waiting_call_registry,
             listeners,
-            open,
+            liveness,
             call_id_counter: Arc::new(AtomicUsize::new(0)),
+            hooks,
+            call_timeout: Mutex::new(DEFAULT_CALL_TIMEOUT),
         })
     }
 
+    /// Lets us say why the connection closed when it was because the browser exited: `probe`
+    /// should return the browser process' exit status once it has one.
+    pub fn set_exit_probe<F>(&self, probe: F)
+    where
+        F: Fn() -> Option<ExitStatus> + Send + 'static,
+    {
+        self.liveness.set_exit_probe(Box::new(probe));
+    }
+
+    /// Call `callback` once the connection to the browser ends, or right away if it already has.
+    pub fn on_disconnect<F>(&self, callback: F)
+    where
+        F: Fn(&DisconnectReason) + Send + 'static,
+    {
+        self.liveness.on_disconnect(Box::new(callback));
+    }
+
+    /// Why the connection to the browser ended, or `None` if it's still open.
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        self.liveness.disconnect_reason()
+    }
+
+    /// Whether the target of this session has crashed, after which calls on it fail with
+    /// `TargetCrashed`.
+    pub fn is_crashed(&self, session_id: &SessionId) -> bool {
+        self.liveness.is_crashed(session_id)
+    }
+
+    /// Have `hook` observe all calls, responses and events from now on.
+    pub fn add_hook(&self, hook: Arc<dyn TransportHook>) {
+        self.hooks.add(hook);
+    }
+
     /// Returns a number based on thread-safe unique counter, incrementing it so that the
     /// next CallId is different.
     pub fn unique_call_id(&self) -> CallId {
         self.call_id_counter.fetch_add(1, Ordering::SeqCst)
     }
 
+    /// How long calls made without a timeout of their own wait for a response.
+    pub fn call_timeout(&self) -> Duration {
+        *self.call_timeout.lock().unwrap()
+    }
+
+    pub fn set_call_timeout(&self, timeout: Duration) {
+        *self.call_timeout.lock().unwrap() = timeout;
+    }
+
     pub fn call_method<C>(
         &self,
         method: C,
@@ -107,16 +247,76 @@ impl Transport {
     where
         C: protocol::Method + serde::Serialize,
     {
+        self.call_method_with_timeout(method, destination, self.call_timeout())
+    }
+
+    /// Make a call, giving up with a `MethodTimeout` error if there's no response within
+    /// `timeout`. A response which arrives after that is ignored.
+    pub fn call_method_with_timeout<C>(
+        &self,
+        method: C,
+        destination: MethodDestination,
+        timeout: Duration,
+    ) -> Result<C::ReturnObject, Error>
+    where
+        C: protocol::Method + serde::Serialize,
+    {
+        self.call_method_observed(method, destination, timeout, true)
+    }
+
+    /// Makes the call, telling hooks about it unless it's only an envelope for another call.
+    fn call_method_observed<C>(
+        &self,
+        method: C,
+        destination: MethodDestination,
+        timeout: Duration,
+        observed: bool,
+    ) -> Result<C::ReturnObject, Error>
+    where
+        C: protocol::Method + serde::Serialize,
+    {
+        // both sending the call and waiting for its response have to fit into `timeout`
+        let started_at = Instant::now();
+        let remaining = || {
+            timeout
+                .checked_sub(started_at.elapsed())
+                .unwrap_or_default()
+        };
+
         // TODO: use get_mut to get exclusive access for entire block... maybe.
-        if !self.open.load(Ordering::SeqCst) {
-            return Err(ConnectionClosed {}.into());
+        if let Some(reason) = self.disconnect_reason() {
+            return Err(ConnectionClosed { reason }.into());
         }
         let call_id = self.unique_call_id();
         let call = method.to_method_call(call_id);
 
-        let message_text = serde_json::to_string(&call)?;
+        let message = serde_json::to_value(&call)?;
+        let message_text = message.to_string();
 
-        let response_rx = self.waiting_call_registry.register_call(call.id);
+        let response_rx = match destination {
+            MethodDestination::Target(ref session_id) => {
+                if self.is_crashed(session_id) {
+                    return Err(TargetCrashed {}.into());
+                }
+                self.waiting_call_registry
+                    .register_target_call(call.id, session_id.clone())
+            }
+            MethodDestination::Browser => self.waiting_call_registry.register_call(call.id),
+        };
+
+        if observed {
+            let session_id = match destination {
+                MethodDestination::Target(ref session_id) => Some(session_id.clone()),
+                MethodDestination::Browser => None,
+            };
+            let call_info = CallInfo {
+                call_id,
+                method: C::NAME,
+                session_id,
+                sent_at: Instant::now(),
+            };
+            self.hooks.call_sent(call_info, &message);
+        }
 
         match destination {
             MethodDestination::Target(session_id) => {
@@ -125,27 +325,66 @@ impl Transport {
                     session_id: Some(session_id.as_str()),
                     message: &message_text,
                 };
-                if let Err(e) = self.call_method_on_browser(target_method) {
+                if let Err(e) = self.call_method_observed(
+                    target_method,
+                    MethodDestination::Browser,
+                    remaining(),
+                    false,
+                ) {
                     error!("Failed to call method on browser");
                     self.waiting_call_registry.unregister_call(call.id);
+                    self.hooks.call_abandoned(call.id);
                     trace!("Unregistered callback: {:?}", call.id);
+                    if e.downcast_ref::<MethodTimeout>().is_some() {
+                        return Err(MethodTimeout {
+                            method: C::NAME,
+                            timeout,
+                        }
+                        .into());
+                    }
                     return Err(e);
                 }
             }
             MethodDestination::Browser => {
-                if let Err(e) = self.web_socket_connection.send_message(&message_text) {
+                if let Err(e) = self.connection.send_message(&message_text) {
                     self.waiting_call_registry.unregister_call(call.id);
+                    self.hooks.call_abandoned(call.id);
                     return Err(e);
                 } else {
-                    trace!("sent method call to browser via websocket");
+                    trace!("sent method call to browser");
                 }
             }
         }
 
         trace!("waiting for response from call registry");
-        let response_result =
-            util::Wait::with_sleep(Duration::from_millis(5)).until(|| response_rx.try_recv().ok());
-        protocol::parse_response::<C::ReturnObject>((response_result?)?)
+        let response = match response_rx.recv_timeout(remaining()) {
+            Ok(Ok(response)) => response,
+            // the call was cancelled, e.g. because its tab crashed
+            Ok(Err(error)) => {
+                self.waiting_call_registry.unregister_call(call.id);
+                self.hooks.call_abandoned(call.id);
+                return Err(error);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                warn!("Method call {} timed out after {:?}", C::NAME, timeout);
+                self.waiting_call_registry.unregister_call(call.id);
+                self.hooks.call_abandoned(call.id);
+                return Err(MethodTimeout {
+                    method: C::NAME,
+                    timeout,
+                }
+                .into());
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                self.waiting_call_registry.unregister_call(call.id);
+                self.hooks.call_abandoned(call.id);
+                let reason = self
+                    .disconnect_reason()
+                    .unwrap_or(DisconnectReason::ConnectionClosed);
+                return Err(ConnectionClosed { reason }.into());
+            }
+        };
+        protocol::parse_response::<C::ReturnObject>(response)
     }
 
     pub fn call_method_on_target<C>(
@@ -189,17 +428,24 @@ impl Transport {
         messages_rx: Receiver<protocol::Message>,
         waiting_call_registry: Arc<WaitingCallRegistry>,
         listeners: Listeners,
-        open: Arc<AtomicBool>,
-        conn: Arc<WebSocketConnection>,
+        liveness: Arc<Liveness>,
+        conn: Arc<dyn Connection>,
+        hooks: Arc<Hooks>,
+        idle_browser_timeout: Duration,
     ) {
         trace!("Starting handle_incoming_messages");
         std::thread::spawn(move || {
             trace!("Inside handle_incoming_messages thread");
             // this iterator calls .recv() under the hood, so can block thread forever
             // hence need for Connection Shutdown
+            let mut idle = false;
             loop {
-                match messages_rx.recv_timeout(Duration::from_millis(20_000)) {
-                    Err(_) => {
+                match messages_rx.recv_timeout(idle_browser_timeout) {
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        idle = true;
+                        break;
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
                         break;
                     }
                     Ok(message) => {
@@ -210,6 +456,7 @@ impl Transport {
                                 break;
                             }
                             Message::Response(response_to_browser_method_call) => {
+                                hooks.response_received(&response_to_browser_method_call);
                                 if waiting_call_registry
                                     .resolve_call(response_to_browser_method_call)
                                     .is_err()
@@ -229,17 +476,30 @@ impl Transport {
                                     {
                                         match target_message {
                                             Message::Event(target_event) => {
+                                                hooks.event_received(
+                                                    &target_event,
+                                                    Some(&session_id),
+                                                );
+                                                if let Event::TargetCrashed(_) = target_event {
+                                                    liveness.target_crashed(session_id.clone());
+                                                    waiting_call_registry
+                                                        .cancel_target_calls(&session_id, || {
+                                                            TargetCrashed {}.into()
+                                                        });
+                                                }
                                                 if let Some(tx) = listeners
                                                     .lock()
                                                     .unwrap()
                                                     .get(&ListenerId::SessionId(session_id))
                                                 {
-                                                    tx.send(target_event)
-                                                        .expect("Couldn't send event to listener");
+                                                    if tx.send(target_event).is_err() {
+                                                        trace!("Tab stopped listening to events");
+                                                    }
                                                 }
                                             }
 
                                             Message::Response(resp) => {
+                                                hooks.response_received(&resp);
                                                 if waiting_call_registry.resolve_call(resp).is_err()
                                                 {
                                                     warn!("The browser registered a call but then closed its receiving channel");
@@ -251,12 +511,13 @@ impl Transport {
                                     } else {
                                         trace!(
                                             "Message from target isn't recognised: {:?}",
-                                            &raw_message[..30]
+                                            raw_message
                                         );
                                     }
                                 }
 
                                 _ => {
+                                    hooks.event_received(&browser_event, None);
                                     if let Some(tx) =
                                         listeners.lock().unwrap().get(&ListenerId::Browser)
                                     {
@@ -274,14 +535,30 @@ impl Transport {
 
             trace!("Shutting down message handling loop");
 
-            // Need to do this because otherwise WS thread might block forever
+            let reason = if idle {
+                DisconnectReason::Idle
+            } else {
+                liveness
+                    .browser_exit()
+                    .unwrap_or(DisconnectReason::ConnectionClosed)
+            };
+
+            // Need to do this because otherwise the connection's thread might block forever
             conn.shutdown();
 
-            open.store(false, Ordering::SeqCst);
-            waiting_call_registry.cancel_outstanding_method_calls();
-            let mut listeners = listeners.lock().unwrap();
-            *listeners = HashMap::new();
-            trace!("cleared listeners, I think");
+            liveness.disconnected(reason.clone());
+            waiting_call_registry.cancel_outstanding_method_calls(|| {
+                ConnectionClosed {
+                    reason: reason.clone(),
+                }
+                .into()
+            });
+            {
+                let mut listeners = listeners.lock().unwrap();
+                *listeners = HashMap::new();
+                trace!("cleared listeners, I think");
+            }
+            liveness.notify_disconnected();
         });
     }
 }
@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+use failure::{Error, Fail};
+use log::*;
+
+use super::{Connection, SessionRecorder};
+use crate::protocol;
+
+#[derive(Debug, Fail)]
+#[fail(display = "The pipe to the browser has been closed")]
+pub struct PipeClosed {}
+
+/// A connection to a browser launched with `--remote-debugging-pipe`.
+///
+/// Chrome reads method calls from its fd 3 and writes responses and events to its fd 4, each
+/// message being a JSON object terminated by a NUL byte.
+#[derive(Debug)]
+pub struct PipeConnection {
+    writer: Mutex<Option<File>>,
+    recorder: Option<Arc<SessionRecorder>>,
+}
+
+impl PipeConnection {
+    pub fn new(
+        to_browser: File,
+        from_browser: File,
+        messages_tx: mpsc::Sender<protocol::Message>,
+        recorder: Option<Arc<SessionRecorder>>,
+    ) -> Self {
+        let incoming_recorder = recorder.clone();
+        std::thread::spawn(move || {
+            trace!("Starting pipe msg dispatching loop");
+            Self::dispatch_incoming_messages(
+                BufReader::new(from_browser),
+                messages_tx,
+                incoming_recorder,
+            );
+            trace!("Quit pipe msg dispatching loop");
+        });
+
+        Self {
+            writer: Mutex::new(Some(to_browser)),
+            recorder,
+        }
+    }
+
+    fn dispatch_incoming_messages<R: BufRead>(
+        mut reader: R,
+        messages_tx: mpsc::Sender<protocol::Message>,
+        recorder: Option<Arc<SessionRecorder>>,
+    ) {
+        let mut buffer = Vec::new();
+        loop {
+            buffer.clear();
+            match reader.read_until(0, &mut buffer) {
+                Ok(0) => {
+                    trace!("Browser closed its end of the pipe");
+                    break;
+                }
+                Err(err) => {
+                    warn!("{}", err);
+                    break;
+                }
+                Ok(_) => {
+                    if buffer.last() == Some(&0) {
+                        buffer.pop();
+                    }
+                    let message_string = String::from_utf8_lossy(&buffer);
+                    if let Some(recorder) = &recorder {
+                        recorder.record_received(&message_string);
+                    }
+                    if let Ok(message) = protocol::parse_raw_message(&message_string) {
+                        if messages_tx.send(message).is_err() {
+                            break;
+                        }
+                    } else {
+                        debug!(
+                            "Incoming message isn't recognised as event or method response: {}",
+                            message_string
+                        );
+                    }
+                }
+            }
+        }
+
+        trace!("Sending shutdown message to message handling loop");
+        if messages_tx
+            .send(protocol::Message::ConnectionShutdown)
+            .is_err()
+        {
+            warn!("Couldn't send message to transport loop telling it to shut down")
+        }
+    }
+}
+
+impl Connection for PipeConnection {
+    fn send_message(&self, message_text: &str) -> Result<(), Error> {
+        if let Some(recorder) = &self.recorder {
+            recorder.record_sent(message_text);
+        }
+        let mut writer = self.writer.lock().unwrap();
+        let writer = writer.as_mut().ok_or(PipeClosed {})?;
+        writer.write_all(message_text.as_bytes())?;
+        writer.write_all(&[0])?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn shutdown(&self) {
+        trace!("Closing pipe to the browser");
+        // Dropping our end makes the browser see EOF, which it answers by closing its own.
+        self.writer.lock().unwrap().take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_messages_on_nul_bytes() {
+        env_logger::try_init().unwrap_or(());
+
+        let (messages_tx, messages_rx) = mpsc::channel();
+        let input = "{\"id\":1,\"result\":{}}\0not json\0{\"id\":2,\"result\":{\"a\":1}}\0";
+        PipeConnection::dispatch_incoming_messages(input.as_bytes(), messages_tx, None);
+
+        let call_ids: Vec<_> = messages_rx
+            .iter()
+            .map(|message| match message {
+                protocol::Message::Response(response) => Some(response.call_id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![Some(1), Some(2), None], call_ids);
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use failure::{Error, Fail};
+use log::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::Connection;
+use crate::protocol::{self, CallId};
+
+#[derive(Debug, Fail)]
+#[fail(
+    display = "Recording has no more calls of {} left to replay (message: {})",
+    method, message
+)]
+pub struct ReplayMismatch {
+    pub method: String,
+    pub message: String,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "The replayed browser connection has been shut down")]
+pub struct ReplayConnectionClosed {}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// A single line of a session recording.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedMessage {
+    /// Seconds since the Unix epoch.
+    pub timestamp: f64,
+    pub direction: Direction,
+    /// The target session the message was sent to or came from, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub message: Value,
+}
+
+impl RecordedMessage {
+    fn new(direction: Direction, message_text: &str) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs_f64())
+            .unwrap_or_default();
+        let message = serde_json::from_str(message_text)
+            .unwrap_or_else(|_| Value::String(message_text.to_string()));
+        let session_id = message["params"]["sessionId"]
+            .as_str()
+            .map(ToString::to_string);
+        Self {
+            timestamp,
+            direction,
+            session_id,
+            message,
+        }
+    }
+
+    /// The method called, looking inside `Target.sendMessageToTarget` for calls on tabs.
+    fn method(&self) -> String {
+        let method = self.message["method"].as_str().unwrap_or_default();
+        match self.inner_message() {
+            Some(inner) => inner["method"].as_str().unwrap_or(method).to_string(),
+            None => method.to_string(),
+        }
+    }
+
+    /// The message to or from a tab carried by this one.
+    fn inner_message(&self) -> Option<Value> {
+        self.message["params"]["message"]
+            .as_str()
+            .and_then(|text| serde_json::from_str(text).ok())
+    }
+
+    /// Ids of the calls this message makes or answers, outermost first.
+    fn call_ids(&self) -> Vec<CallId> {
+        let inner_id = self.inner_message().and_then(|inner| call_id(&inner));
+        call_id(&self.message).into_iter().chain(inner_id).collect()
+    }
+
+    /// This message with its call ids swapped for the ones in `ids`, or `None` if one of them
+    /// hasn't been made yet in this run.
+    fn with_call_ids(&self, ids: &HashMap<CallId, CallId>) -> Option<Value> {
+        let mut message = self.message.clone();
+        if let Some(id) = call_id(&message) {
+            message["id"] = (*ids.get(&id)?).into();
+        }
+        if let Some(mut inner) = self.inner_message() {
+            if let Some(id) = call_id(&inner) {
+                inner["id"] = (*ids.get(&id)?).into();
+                message["params"]["message"] = inner.to_string().into();
+            }
+        }
+        Some(message)
+    }
+}
+
+fn call_id(message: &Value) -> Option<CallId> {
+    message["id"]
+        .as_u64()
+        .and_then(|id| CallId::try_from(id).ok())
+}
+
+/// Writes every message exchanged with the browser to a JSON lines file, one
+/// [RecordedMessage](struct.RecordedMessage.html) per line.
+#[derive(Debug)]
+pub struct SessionRecorder {
+    file: Mutex<File>,
+}
+
+impl SessionRecorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Self {
+            file: Mutex::new(File::create(path)?),
+        })
+    }
+
+    pub fn record_sent(&self, message_text: &str) {
+        self.record(RecordedMessage::new(Direction::Sent, message_text));
+    }
+
+    pub fn record_received(&self, message_text: &str) {
+        self.record(RecordedMessage::new(Direction::Received, message_text));
+    }
+
+    fn record(&self, recorded: RecordedMessage) {
+        let mut line = serde_json::to_string(&recorded).unwrap();
+        line.push('\n');
+        // written in one go so that lines from different threads don't interleave
+        if let Err(err) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            warn!("Couldn't record message: {}", err);
+        }
+    }
+}
+
+/// A fake browser which answers method calls from a recording made by a `SessionRecorder`,
+/// for reproducing a run without launching Chrome:
+///
+/// ```rust,no_run
+/// # use failure::Error;
+/// # fn main() -> Result<(), Error> {
+/// #
+/// use headless_chrome::Browser;
+/// use headless_chrome::browser::transport::ReplayConnection;
+///
+/// let browser = Browser::with_connection(|messages_tx| {
+///     Ok(ReplayConnection::open("failing_run.jsonl", messages_tx)?)
+/// })?;
+/// #
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Each call is matched with the next recorded call of the same method, and answered with the
+/// responses and events which were received after it, in recorded order. A response is held
+/// back (along with everything recorded after it) until the call it answers has been made, and
+/// so is anything received after a call which hasn't been replayed yet.
+/// Calls which the recording runs out of fail with `ReplayMismatch`.
+#[derive(Debug)]
+pub struct ReplayConnection {
+    state: Mutex<ReplayState>,
+    messages_tx: Mutex<Option<mpsc::Sender<protocol::Message>>>,
+}
+
+#[derive(Debug)]
+struct ReplayState {
+    /// Recorded calls, taken as they are replayed.
+    calls: Vec<Option<RecordedMessage>>,
+    /// Recorded responses and events, each with the index of the last call made before it.
+    received: VecDeque<(Option<usize>, RecordedMessage)>,
+    /// Maps ids of recorded calls to the ids of the same calls in this run.
+    call_ids: HashMap<CallId, CallId>,
+}
+
+impl ReplayConnection {
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        messages_tx: mpsc::Sender<protocol::Message>,
+    ) -> Result<Self, Error> {
+        let mut recording = vec![];
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                recording.push(serde_json::from_str(&line)?);
+            }
+        }
+        Self::new(recording, messages_tx)
+    }
+
+    pub fn new(
+        recording: Vec<RecordedMessage>,
+        messages_tx: mpsc::Sender<protocol::Message>,
+    ) -> Result<Self, Error> {
+        let mut calls = vec![];
+        let mut received = VecDeque::new();
+        for recorded in recording {
+            match recorded.direction {
+                Direction::Sent => calls.push(Some(recorded)),
+                Direction::Received => received.push_back((calls.len().checked_sub(1), recorded)),
+            }
+        }
+
+        let connection = Self {
+            state: Mutex::new(ReplayState {
+                calls,
+                received,
+                call_ids: HashMap::new(),
+            }),
+            messages_tx: Mutex::new(Some(messages_tx)),
+        };
+        // events the browser sent before we called anything
+        connection.play_received()?;
+        Ok(connection)
+    }
+
+    /// Sends on every recorded message up to the first one which answers, or was received
+    /// after, a call not yet made.
+    fn play_received(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().unwrap();
+        let messages_tx = self.messages_tx.lock().unwrap();
+        let messages_tx = messages_tx.as_ref().ok_or(ReplayConnectionClosed {})?;
+
+        while let Some((after_call, recorded)) = state.received.front() {
+            if let Some(index) = *after_call {
+                if state.calls[index].is_some() {
+                    break;
+                }
+            }
+            let message = match recorded.with_call_ids(&state.call_ids) {
+                Some(message) => message,
+                None => break,
+            };
+            state.received.pop_front();
+
+            match serde_json::from_value(message) {
+                Ok(message) => messages_tx.send(message)?,
+                Err(_) => trace!("Skipping unrecognised recorded message"),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Connection for ReplayConnection {
+    fn send_message(&self, message_text: &str) -> Result<(), Error> {
+        let call = RecordedMessage::new(Direction::Sent, message_text);
+        let method = call.method();
+        {
+            let mut state = self.state.lock().unwrap();
+            let recorded = state
+                .calls
+                .iter_mut()
+                .find(|recorded| match recorded {
+                    Some(recorded) => recorded.method() == method,
+                    None => false,
+                })
+                .and_then(Option::take)
+                .ok_or_else(|| ReplayMismatch {
+                    method: method.clone(),
+                    message: message_text.to_string(),
+                })?;
+            trace!("Replaying answer to {}", method);
+
+            for (recorded_id, id) in recorded.call_ids().into_iter().zip(call.call_ids()) {
+                state.call_ids.insert(recorded_id, id);
+            }
+        }
+        self.play_received()
+    }
+
+    fn shutdown(&self) {
+        trace!("Shutting down replay connection");
+        if let Some(messages_tx) = self.messages_tx.lock().unwrap().take() {
+            if messages_tx
+                .send(protocol::Message::ConnectionShutdown)
+                .is_err()
+            {
+                trace!("Transport loop had already stopped");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::browser::transport::{SessionId, Transport};
+    use crate::protocol::page::methods::Navigate;
+    use crate::protocol::target::methods::{AttachToTarget, SetDiscoverTargets};
+
+    fn navigate(transport: &Transport) -> Result<String, Error> {
+        transport.call_method_on_browser(SetDiscoverTargets { discover: true })?;
+        let session_id: SessionId = transport
+            .call_method_on_browser(AttachToTarget {
+                target_id: "TARGET",
+                flatten: None,
+            })?
+            .session_id
+            .into();
+        Ok(transport
+            .call_method_on_target(
+                session_id,
+                Navigate {
+                    url: "http://example.com",
+                },
+            )?
+            .frame_id)
+    }
+
+    #[test]
+    fn replays_recorded_session() {
+        env_logger::try_init().unwrap_or(());
+
+        let recording_file = tempfile::NamedTempFile::new().unwrap();
+        let recorder = SessionRecorder::create(recording_file.path()).unwrap();
+        recorder.record_sent(
+            r#"{"method":"Target.setDiscoverTargets","id":0,"params":{"discover":true}}"#,
+        );
+        recorder.record_received(
+            r#"{"method":"Target.targetCreated","params":{"targetInfo":{"targetId":"TARGET","type":"page","title":"","url":"about:blank","attached":false}}}"#,
+        );
+        recorder.record_received(r#"{"id":0,"result":{}}"#);
+        recorder.record_sent(
+            r#"{"method":"Target.attachToTarget","id":1,"params":{"targetId":"TARGET"}}"#,
+        );
+        recorder.record_received(r#"{"id":1,"result":{"sessionId":"SESSION"}}"#);
+        recorder.record_sent(
+            r#"{"method":"Target.sendMessageToTarget","id":3,"params":{"sessionId":"SESSION","message":"{\"method\":\"Page.navigate\",\"id\":2,\"params\":{\"url\":\"http://example.com\"}}"}}"#,
+        );
+        recorder.record_received(
+            r#"{"method":"Target.receivedMessageFromTarget","params":{"sessionId":"SESSION","targetId":"TARGET","message":"{\"id\":2,\"result\":{\"frameId\":\"MAIN_FRAME\"}}"}}"#,
+        );
+        recorder.record_received(r#"{"id":3,"result":{}}"#);
+
+        let transport = Transport::with_connection(
+            |messages_tx| ReplayConnection::open(recording_file.path(), messages_tx),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        let browser_events_rx = transport.listen_to_browser_events();
+        // so that call ids differ from the recorded ones
+        transport.unique_call_id();
+
+        assert_eq!("MAIN_FRAME", navigate(&transport).unwrap());
+        match browser_events_rx.try_recv().unwrap() {
+            protocol::Event::TargetCreated(_) => {}
+            event => panic!("Unexpected event: {:?}", event),
+        }
+
+        let error = transport
+            .call_method_on_browser(SetDiscoverTargets { discover: true })
+            .unwrap_err();
+        assert!(error.downcast_ref::<ReplayMismatch>().is_some());
+    }
+
+    #[test]
+    fn records_session_ids() {
+        let recorded = RecordedMessage::new(
+            Direction::Sent,
+            r#"{"method":"Target.sendMessageToTarget","id":1,"params":{"sessionId":"SESSION","message":"{\"method\":\"DOM.enable\",\"id\":0}"}}"#,
+        );
+        assert_eq!(Some("SESSION".to_string()), recorded.session_id);
+        assert_eq!("DOM.enable", recorded.method());
+        assert_eq!(vec![1, 0], recorded.call_ids());
+    }
+}
@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use std::sync::mpsc;
 use std::sync::Mutex;
 
-use super::ConnectionClosed;
+use super::SessionId;
 use crate::protocol::{CallId, Response};
 
 trait IdentifiableResponse {
@@ -14,6 +14,8 @@ trait IdentifiableResponse {
 #[derive(Debug)]
 pub struct WaitingCallRegistry {
     calls: Mutex<HashMap<CallId, mpsc::Sender<Result<Response, Error>>>>,
+    /// The sessions calls on targets were made on.
+    sessions: Mutex<HashMap<CallId, SessionId>>,
 }
 
 impl IdentifiableResponse for Response {
@@ -25,8 +27,9 @@ impl IdentifiableResponse for Response {
 impl Default for WaitingCallRegistry {
     fn default() -> Self {
         let calls = Mutex::new(HashMap::new());
+        let sessions = Mutex::new(HashMap::new());
 
-        Self { calls }
+        Self { calls, sessions }
     }
 }
 
@@ -37,11 +40,16 @@ impl WaitingCallRegistry {
 
     pub fn resolve_call(&self, response: Response) -> Result<(), Error> {
         trace!("Resolving call");
-        let waiting_call_tx: mpsc::Sender<Result<Response, Error>> = {
+        let waiting_call_tx = {
             let mut waiting_calls = self.calls.lock().unwrap();
-            waiting_calls.remove(&response.call_id()).unwrap()
+            waiting_calls.remove(&response.call_id())
         };
-        waiting_call_tx.send(Ok(response))?;
+        self.sessions.lock().unwrap().remove(&response.call_id());
+        match waiting_call_tx {
+            Some(waiting_call_tx) => waiting_call_tx.send(Ok(response))?,
+            // e.g. the call timed out, and gave up waiting
+            None => trace!("Nobody waiting for response to {:?}", response.call_id()),
+        }
         Ok(())
     }
 
@@ -53,31 +61,71 @@ impl WaitingCallRegistry {
         rx
     }
 
+    /// Like `register_call`, but for a call on a target, so that it can be cancelled along
+    /// with the target's other calls by `cancel_target_calls`.
+    pub fn register_target_call(
+        &self,
+        call_id: CallId,
+        session_id: SessionId,
+    ) -> mpsc::Receiver<Result<Response, Error>> {
+        self.sessions.lock().unwrap().insert(call_id, session_id);
+        self.register_call(call_id)
+    }
+
     pub fn unregister_call(&self, call_id: CallId) {
         trace!("Deregistering call");
         let mut calls = self.calls.lock().unwrap();
-        calls.remove(&call_id).unwrap();
+        calls.remove(&call_id);
+        self.sessions.lock().unwrap().remove(&call_id);
     }
 
-    // TODO: make it so we can pass in whatever error we want here
-    // to make it less dependent on browser::transport
-    pub fn cancel_outstanding_method_calls(&self) {
+    /// Fail every waiting call with the error `make_error` gives.
+    pub fn cancel_outstanding_method_calls<F>(&self, make_error: F)
+    where
+        F: Fn() -> Error,
+    {
         trace!("Cancelling outstanding method calls");
         let calls = self.calls.lock().unwrap();
         for (call_id, sender) in calls.iter() {
             trace!(
-                "Telling waiting method call {:?} that the connection closed",
+                "Telling waiting method call {:?} it won't be answered",
                 call_id
             );
-            if let Err(e) = sender.send(Err(ConnectionClosed {}.into())) {
+            if let Err(e) = sender.send(Err(make_error())) {
                 trace!(
-                    "Couldn't send ConnectionClosed to waiting method call: {:?} because {:?}",
+                    "Couldn't cancel waiting method call: {:?} because {:?}",
                     call_id,
                     e
                 );
             }
         }
     }
+
+    /// Fail the waiting calls made on the given session with the error `make_error` gives.
+    pub fn cancel_target_calls<F>(&self, session_id: &SessionId, make_error: F)
+    where
+        F: Fn() -> Error,
+    {
+        trace!("Cancelling method calls on {:?}", session_id);
+        let call_ids: Vec<CallId> = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let call_ids = sessions
+                .iter()
+                .filter(|(_, call_session_id)| *call_session_id == session_id)
+                .map(|(call_id, _)| *call_id)
+                .collect();
+            sessions.retain(|_, call_session_id| call_session_id != session_id);
+            call_ids
+        };
+        let mut calls = self.calls.lock().unwrap();
+        for call_id in call_ids {
+            if let Some(sender) = calls.remove(&call_id) {
+                if sender.send(Err(make_error())).is_err() {
+                    trace!("Call {:?} had already stopped waiting", call_id);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -114,4 +162,22 @@ mod tests {
         assert_eq!(resp2_clone, call_rx2.recv().unwrap().unwrap());
         assert_eq!(resp_clone, call_rx.recv().unwrap().unwrap());
     }
+
+    #[test]
+    fn ignore_responses_to_abandoned_calls() {
+        env_logger::try_init().unwrap_or(());
+
+        let waiting_calls = WaitingCallRegistry::new();
+        let _call_rx = waiting_calls.register_call(7);
+        waiting_calls.unregister_call(7);
+        // e.g. the call timed out just before its response arrived
+        waiting_calls.unregister_call(7);
+
+        let late_response = Response {
+            call_id: 7,
+            result: Some(json! {true}),
+            error: None,
+        };
+        assert!(waiting_calls.resolve_call(late_response).is_ok());
+    }
 }
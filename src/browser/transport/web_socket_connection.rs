@@ -8,11 +8,13 @@ use websocket::stream::sync::TcpStream;
 use websocket::WebSocketError;
 use websocket::{ClientBuilder, OwnedMessage};
 
+use super::{Connection, SessionRecorder};
 use crate::protocol;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 pub struct WebSocketConnection {
     sender: Mutex<websocket::sender::Writer<TcpStream>>,
+    recorder: Option<Arc<SessionRecorder>>,
 }
 
 // TODO websocket::sender::Writer is not :Debug...
@@ -26,47 +28,52 @@ impl WebSocketConnection {
     pub fn new(
         ws_url: &str,
         target_messages_tx: mpsc::Sender<protocol::Message>,
+        recorder: Option<Arc<SessionRecorder>>,
     ) -> Result<Self, Error> {
         let connection = Self::websocket_connection(&ws_url)?;
         let (websocket_receiver, sender) = connection.split()?;
 
+        let incoming_recorder = recorder.clone();
         std::thread::spawn(move || {
             trace!("Starting msg dispatching loop");
-            Self::dispatch_incoming_messages(websocket_receiver, target_messages_tx);
+            Self::dispatch_incoming_messages(
+                websocket_receiver,
+                target_messages_tx,
+                incoming_recorder,
+            );
             trace!("Quit loop msg dispatching loop");
         });
 
         Ok(Self {
             sender: Mutex::new(sender),
+            recorder,
         })
     }
 
-    pub fn shutdown(&self) {
-        trace!("Shutting down WebSocket connection");
-        if self.sender.lock().unwrap().shutdown_all().is_err() {
-            warn!("Couldn't shut down WS connection");
-        }
-    }
-
     fn dispatch_incoming_messages(
         mut receiver: websocket::receiver::Reader<TcpStream>,
         messages_tx: mpsc::Sender<protocol::Message>,
+        recorder: Option<Arc<SessionRecorder>>,
     ) {
         for ws_message in receiver.incoming_messages() {
             match ws_message {
-                Err(error) => match error {
-                    WebSocketError::NoDataAvailable => {
-                        warn!("{}", error);
-                        break;
-                    }
-                    WebSocketError::IoError(err) => {
-                        warn!("{}", err);
-                        break;
+                Err(error) => {
+                    match error {
+                        WebSocketError::NoDataAvailable => warn!("{}", error),
+                        WebSocketError::IoError(err) => warn!("{}", err),
+                        _ => warn!("Unhandled WebSocket error: {:?}", error),
                     }
-                    _ => panic!("Unhandled WebSocket error: {:?}", error),
-                },
+                    break;
+                }
+                Ok(OwnedMessage::Close(close_data)) => {
+                    debug!("Browser closed the WebSocket: {:?}", close_data);
+                    break;
+                }
                 Ok(message) => {
                     if let OwnedMessage::Text(message_string) = message {
+                        if let Some(recorder) = &recorder {
+                            recorder.record_received(&message_string);
+                        }
                         if let Ok(message) = protocol::parse_raw_message(&message_string) {
                             if messages_tx.send(message).is_err() {
                                 break;
@@ -78,7 +85,7 @@ impl WebSocketConnection {
                             );
                         }
                     } else {
-                        panic!("Got a weird message: {:?}", message)
+                        trace!("Ignoring non-text WebSocket message: {:?}", message);
                     }
                 }
             }
@@ -100,11 +107,23 @@ impl WebSocketConnection {
 
         Ok(client)
     }
+}
 
-    pub fn send_message(&self, message_text: &str) -> Result<(), Error> {
+impl Connection for WebSocketConnection {
+    fn send_message(&self, message_text: &str) -> Result<(), Error> {
+        if let Some(recorder) = &self.recorder {
+            recorder.record_sent(message_text);
+        }
         let message = websocket::Message::text(message_text);
         let mut sender = self.sender.lock().unwrap();
         sender.send_message(&message)?;
         Ok(())
     }
+
+    fn shutdown(&self) {
+        trace!("Shutting down WebSocket connection");
+        if self.sender.lock().unwrap().shutdown_all().is_err() {
+            warn!("Couldn't shut down WS connection");
+        }
+    }
 }
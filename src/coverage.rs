@@ -0,0 +1,748 @@
+//! For turning the JavaScript coverage Chrome reports into LCOV and Istanbul reports.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::ops::Range;
+
+use log::*;
+use serde_json::{json, Map, Value};
+
+use crate::browser::Tab;
+use crate::protocol::profiler::{CoverageRange, ScriptCoverage};
+use crate::source_map::SourceMap;
+use crate::Error;
+
+/// A position in a script, with lines counting from 1 and columns (in UTF-16 code units, like
+/// JavaScript's string indices) from 0, as Istanbul has them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// How many times the code on a line was run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineCoverage {
+    /// The least number of times any of the code on the line was run, so that a line is only
+    /// covered once all of it has been.
+    pub count: u64,
+    /// Where the code on the line starts, ignoring whitespace.
+    pub start_column: u32,
+    /// Where it ends, or for lines mapped back to with a source map, where the last piece of
+    /// code on it starts.
+    pub end_column: u32,
+}
+
+/// How many times a function was called.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCoverage {
+    /// Its name, or `(anonymous_n)` for the n-th function in the script if it hasn't got one.
+    pub name: String,
+    pub start: Position,
+    pub end: Position,
+    pub count: u64,
+}
+
+/// The coverage of a single script, original source file or style sheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileCoverage {
+    pub url: String,
+    /// Keyed by line number, leaving out lines without any code.
+    pub lines: BTreeMap<u32, LineCoverage>,
+    /// In the order they appear in the script, leaving out its top level.
+    pub functions: Vec<FunctionCoverage>,
+}
+
+/// Which rules of a style sheet a tab used, as returned by `Tab::stop_css_coverage`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StyleSheetCoverage {
+    pub style_sheet_id: String,
+    /// The style sheet's URL, or for `<style>` elements, the document's.
+    pub url: String,
+    pub text: String,
+    /// Where a `<style>` element's style sheet starts in its document, counting from 0.
+    pub start_line: u32,
+    pub start_column: u32,
+    /// The offsets in `text` (in UTF-16 code units) of the rules that were used.
+    pub used: Vec<Range<u32>>,
+    /// And of those that weren't.
+    pub unused: Vec<Range<u32>>,
+}
+
+/// Line and function coverage of the scripts a page ran, merged from any number of snapshots
+/// taken with `Tab::take_precise_js_coverage`, which can be written out as LCOV or as
+/// Istanbul's JSON, along with line coverage of its style sheets from
+/// `Tab::stop_css_coverage`.
+///
+/// ```rust,no_run
+/// # use failure::Error;
+/// # fn main() -> Result<(), Error> {
+/// #
+/// use headless_chrome::coverage::CoverageReport;
+/// # use headless_chrome::{Browser, LaunchOptionsBuilder};
+/// # let browser = Browser::new(LaunchOptionsBuilder::default().build().unwrap())?;
+/// # let tab = browser.wait_for_initial_tab()?;
+///
+/// tab.enable_profiler()?.start_js_coverage()?;
+/// tab.start_css_coverage()?;
+/// let mut report = CoverageReport::new();
+///
+/// tab.navigate_to("http://localhost:8000")?.wait_until_navigated()?;
+/// report.add(&tab, &tab.take_precise_js_coverage()?)?;
+/// tab.wait_for_element("button")?.click()?;
+/// report.add(&tab, &tab.take_precise_js_coverage()?)?;
+/// report.add_css(&tab.stop_css_coverage()?);
+///
+/// report.write_lcov(std::fs::File::create("lcov.info")?)?;
+/// #
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CoverageReport {
+    files: BTreeMap<String, FileCoverage>,
+}
+
+impl CoverageReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a snapshot of coverage taken in `tab`, which we fetch the sources of its scripts
+    /// from with the debugger, so it needs to still have them loaded. The debugger is enabled
+    /// for the duration unless it already was, in which case it's left enabled.
+    ///
+    /// Scripts with source maps are reported as the original sources they map back to, which
+    /// can mean fetching the maps over HTTP (see `Tab::get_source_map`). Scripts without a
+    /// URL, like those passed to `eval`, or whose sources can't be had, are left out.
+    pub fn add(&mut self, tab: &Tab, coverage: &[ScriptCoverage]) -> Result<(), Error> {
+        let scripts: Vec<_> = coverage
+            .iter()
+            .filter(|script| !script.url.is_empty())
+            .collect();
+        if scripts.is_empty() {
+            return Ok(());
+        }
+
+        let enable_debugger = !tab.is_debugger_enabled();
+        if enable_debugger {
+            tab.enable_debugger()?;
+        }
+        let script_ids: Vec<&str> = scripts
+            .iter()
+            .map(|script| script.script_id.as_str())
+            .collect();
+        tab.wait_to_hear_about(&script_ids, &[]);
+        let sources: Vec<_> = scripts
+            .iter()
+            .map(|script| tab.get_script_source(&script.script_id))
+            .collect();
+        if enable_debugger {
+            tab.disable_debugger()?;
+        }
+
+        for (script, source) in scripts.into_iter().zip(sources) {
+            let source = match source {
+                Ok(source) => source,
+                Err(error) => {
+                    warn!("Leaving out {}: {}", script.url, error);
+                    continue;
+                }
+            };
+            match tab.get_source_map(&script.script_id) {
+                Ok(Some(source_map)) => self.add_mapped_script(script, &source, &source_map),
+                Ok(None) => self.add_script(script, &source),
+                Err(error) => {
+                    warn!("Reporting {} as it is: {}", script.url, error);
+                    self.add_script(script, &source);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the coverage of a script whose source we already have.
+    pub fn add_script(&mut self, script: &ScriptCoverage, source: &str) {
+        let lines = SourceLines::new(source);
+        let counts = counts_by_offset(script, lines.len);
+
+        let file = self.file(&script.url);
+        file.merge_lines(line_coverage(source, &counts));
+        for (name, range) in functions(script) {
+            file.merge_function(FunctionCoverage {
+                name,
+                start: lines.position(range.start_offset),
+                end: lines.position(range.end_offset),
+                count: u64::from(range.count),
+            });
+        }
+    }
+
+    /// Add the coverage of a bundled or minified script whose source we already have,
+    /// attributing it to the original sources its source map points to.
+    ///
+    /// Code without mappings, like that a bundler adds, is left out.
+    pub fn add_mapped_script(
+        &mut self,
+        script: &ScriptCoverage,
+        source: &str,
+        source_map: &SourceMap,
+    ) {
+        let lines = SourceLines::new(source);
+        let counts = counts_by_offset(script, lines.len);
+
+        let mut mapped_lines: BTreeMap<usize, BTreeMap<u32, LineCoverage>> = BTreeMap::new();
+        for mapping in source_map.mappings() {
+            let (line, column) = mapping.generated;
+            let count = match lines
+                .offset(line, column)
+                .and_then(|offset| counts.get(offset as usize))
+            {
+                Some(Some(count)) => u64::from(*count),
+                _ => continue,
+            };
+            let (original_line, original_column) = mapping.original;
+            mapped_lines
+                .entry(mapping.source)
+                .or_default()
+                .entry(original_line + 1)
+                .and_modify(|coverage| {
+                    coverage.count = coverage.count.min(count);
+                    coverage.start_column = coverage.start_column.min(original_column);
+                    coverage.end_column = coverage.end_column.max(original_column);
+                })
+                .or_insert(LineCoverage {
+                    count,
+                    start_column: original_column,
+                    end_column: original_column,
+                });
+        }
+        for (source, coverage) in mapped_lines {
+            self.file(source_map.source(source)).merge_lines(coverage);
+        }
+
+        let original_position = |offset: u32| {
+            let position = lines.position(offset);
+            let location = source_map.lookup(position.line - 1, position.column)?;
+            let position = Position {
+                line: location.line + 1,
+                column: location.column,
+            };
+            Some((location.source, position))
+        };
+        for (name, range) in functions(script) {
+            let (url, start) = match original_position(range.start_offset) {
+                Some(start) => start,
+                None => continue,
+            };
+            let end = match original_position(range.end_offset) {
+                Some((end_url, end)) if end_url == url && end > start => end,
+                _ => start,
+            };
+            self.file(&url).merge_function(FunctionCoverage {
+                name,
+                start,
+                end,
+                count: u64::from(range.count),
+            });
+        }
+    }
+
+    /// Add which rules of style sheets were used, as returned by `Tab::stop_css_coverage`,
+    /// with a line covered once for each snapshot it had a used rule in, and only those.
+    ///
+    /// The style sheets of `<style>` elements are reported as part of their documents.
+    pub fn add_css(&mut self, coverage: &[StyleSheetCoverage]) {
+        for style_sheet in coverage
+            .iter()
+            .filter(|style_sheet| !style_sheet.url.is_empty())
+        {
+            let lines = SourceLines::new(&style_sheet.text);
+            let mut counts = vec![None; lines.len as usize];
+            let ranges = style_sheet
+                .unused
+                .iter()
+                .map(|range| (range, 0))
+                .chain(style_sheet.used.iter().map(|range| (range, 1)));
+            for (range, count) in ranges {
+                let start = range.start.min(lines.len) as usize;
+                let end = range.end.min(lines.len) as usize;
+                for offset in &mut counts[start..end] {
+                    *offset = Some(count);
+                }
+            }
+
+            let coverage = line_coverage(&style_sheet.text, &counts).into_iter().map(
+                |(line, mut coverage)| {
+                    if line == 1 {
+                        coverage.start_column += style_sheet.start_column;
+                        coverage.end_column += style_sheet.start_column;
+                    }
+                    (line + style_sheet.start_line, coverage)
+                },
+            );
+            self.file(&style_sheet.url).merge_lines(coverage);
+        }
+    }
+
+    fn file(&mut self, url: &str) -> &mut FileCoverage {
+        self.files
+            .entry(url.to_string())
+            .or_insert_with(|| FileCoverage {
+                url: url.to_string(),
+                lines: BTreeMap::new(),
+                functions: vec![],
+            })
+    }
+
+    /// The scripts covered, by URL.
+    pub fn files(&self) -> &BTreeMap<String, FileCoverage> {
+        &self.files
+    }
+
+    /// Write the report in LCOV's tracefile format, as read by e.g. `genhtml` and most CI
+    /// coverage services.
+    pub fn write_lcov<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for file in self.files.values() {
+            writeln!(out, "TN:")?;
+            writeln!(out, "SF:{}", file.url)?;
+            for function in &file.functions {
+                writeln!(out, "FN:{},{}", function.start.line, function.name)?;
+            }
+            for function in &file.functions {
+                writeln!(out, "FNDA:{},{}", function.count, function.name)?;
+            }
+            writeln!(out, "FNF:{}", file.functions.len())?;
+            writeln!(
+                out,
+                "FNH:{}",
+                file.functions.iter().filter(|f| f.count > 0).count()
+            )?;
+            for (line, coverage) in &file.lines {
+                writeln!(out, "DA:{},{}", line, coverage.count)?;
+            }
+            writeln!(out, "LF:{}", file.lines.len())?;
+            writeln!(
+                out,
+                "LH:{}",
+                file.lines.values().filter(|line| line.count > 0).count()
+            )?;
+            writeln!(out, "end_of_record")?;
+        }
+        Ok(())
+    }
+
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = vec![];
+        self.write_lcov(&mut lcov).unwrap();
+        String::from_utf8(lcov).unwrap()
+    }
+
+    /// The report in the format of Istanbul's `coverage-final.json`, with a statement for each
+    /// line, as read by e.g. `nyc report`.
+    pub fn to_istanbul(&self) -> Value {
+        let files: Map<String, Value> = self
+            .files
+            .values()
+            .map(|file| (file.url.clone(), istanbul_file(file)))
+            .collect();
+        Value::Object(files)
+    }
+
+    pub fn write_istanbul<W: Write>(&self, out: W) -> Result<(), Error> {
+        serde_json::to_writer_pretty(out, &self.to_istanbul())?;
+        Ok(())
+    }
+}
+
+impl FileCoverage {
+    fn merge_lines<I: IntoIterator<Item = (u32, LineCoverage)>>(&mut self, lines: I) {
+        for (line, coverage) in lines {
+            self.lines
+                .entry(line)
+                .and_modify(|existing| existing.count += coverage.count)
+                .or_insert(coverage);
+        }
+    }
+
+    fn merge_function(&mut self, function: FunctionCoverage) {
+        match self
+            .functions
+            .iter_mut()
+            .find(|existing| existing.name == function.name && existing.start == function.start)
+        {
+            Some(existing) => existing.count += function.count,
+            None => {
+                self.functions.push(function);
+                self.functions.sort_by_key(|function| function.start);
+            }
+        }
+    }
+}
+
+/// The functions of a script with the ranges they cover, leaving out its top level, which
+/// isn't a function as far as a report is concerned.
+fn functions(script: &ScriptCoverage) -> impl Iterator<Item = (String, &CoverageRange)> {
+    script
+        .functions
+        .iter()
+        .enumerate()
+        .filter_map(|(i, function)| {
+            let range = function.ranges.first()?;
+            if function.function_name.is_empty() && range.start_offset == 0 {
+                return None;
+            }
+            let name = if function.function_name.is_empty() {
+                format!("(anonymous_{})", i)
+            } else {
+                function.function_name.clone()
+            };
+            Some((name, range))
+        })
+}
+
+fn istanbul_file(file: &FileCoverage) -> Value {
+    let location = |start: Position, end: Position| {
+        json!({
+            "start": { "line": start.line, "column": start.column },
+            "end": { "line": end.line, "column": end.column },
+        })
+    };
+
+    let mut statement_map = Map::new();
+    let mut statements = Map::new();
+    for (i, (&line, coverage)) in file.lines.iter().enumerate() {
+        let start = Position {
+            line,
+            column: coverage.start_column,
+        };
+        let end = Position {
+            line,
+            column: coverage.end_column,
+        };
+        statement_map.insert(i.to_string(), location(start, end));
+        statements.insert(i.to_string(), json!(coverage.count));
+    }
+
+    let mut fn_map = Map::new();
+    let mut functions = Map::new();
+    for (i, function) in file.functions.iter().enumerate() {
+        fn_map.insert(
+            i.to_string(),
+            json!({
+                "name": function.name,
+                "decl": location(function.start, function.start),
+                "loc": location(function.start, function.end),
+                "line": function.start.line,
+            }),
+        );
+        functions.insert(i.to_string(), json!(function.count));
+    }
+
+    json!({
+        "path": file.url,
+        "statementMap": statement_map,
+        "fnMap": fn_map,
+        "branchMap": {},
+        "s": statements,
+        "f": functions,
+        "b": {},
+    })
+}
+
+/// Where each line of a script starts, for turning the offsets Chrome reports (in UTF-16 code
+/// units) into positions.
+struct SourceLines {
+    starts: Vec<u32>,
+    len: u32,
+}
+
+impl SourceLines {
+    fn new(source: &str) -> Self {
+        let mut starts = vec![0];
+        let mut len = 0;
+        for c in source.chars() {
+            len += c.len_utf16() as u32;
+            if c == '\n' {
+                starts.push(len);
+            }
+        }
+        Self { starts, len }
+    }
+
+    /// The offset of a line and column, both counting from 0.
+    fn offset(&self, line: u32, column: u32) -> Option<u32> {
+        self.starts.get(line as usize).map(|start| start + column)
+    }
+
+    fn position(&self, offset: u32) -> Position {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        Position {
+            line: line as u32 + 1,
+            column: offset - self.starts[line],
+        }
+    }
+}
+
+/// How many times the code at each offset of a script was run, going by the innermost range
+/// covering it.
+fn counts_by_offset(script: &ScriptCoverage, len: u32) -> Vec<Option<u32>> {
+    let mut ranges: Vec<_> = script
+        .functions
+        .iter()
+        .flat_map(|function| &function.ranges)
+        .collect();
+    // ranges nest, so this puts each one after those it's nested in
+    ranges.sort_by_key(|range| (range.start_offset, Reverse(range.end_offset)));
+
+    let mut counts = vec![None; len as usize];
+    for range in ranges {
+        let start = range.start_offset.min(len) as usize;
+        let end = range.end_offset.min(len) as usize;
+        for count in &mut counts[start..end] {
+            *count = Some(range.count);
+        }
+    }
+    counts
+}
+
+fn line_coverage(source: &str, counts: &[Option<u32>]) -> Vec<(u32, LineCoverage)> {
+    let mut lines = vec![];
+    let mut line = 1;
+    let mut current: Option<LineCoverage> = None;
+    let mut offset = 0;
+    let mut column = 0;
+    for c in source.chars() {
+        let width = c.len_utf16() as u32;
+        if c == '\n' {
+            if let Some(coverage) = current.take() {
+                lines.push((line, coverage));
+            }
+            line += 1;
+            column = 0;
+        } else {
+            if let (false, Some(Some(count))) = (c.is_whitespace(), counts.get(offset as usize)) {
+                let count = u64::from(*count);
+                match current.as_mut() {
+                    Some(coverage) => {
+                        coverage.count = coverage.count.min(count);
+                        coverage.end_column = column + width;
+                    }
+                    None => {
+                        current = Some(LineCoverage {
+                            count,
+                            start_column: column,
+                            end_column: column + width,
+                        });
+                    }
+                }
+            }
+            column += width;
+        }
+        offset += width;
+    }
+    if let Some(coverage) = current {
+        lines.push((line, coverage));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::profiler;
+
+    fn script(url: &str, functions: &[(&str, &[(usize, usize, u32)])]) -> ScriptCoverage {
+        ScriptCoverage {
+            script_id: "1".to_string(),
+            url: url.to_string(),
+            functions: functions
+                .iter()
+                .map(|(name, ranges)| profiler::FunctionCoverage {
+                    function_name: name.to_string(),
+                    ranges: ranges
+                        .iter()
+                        .map(|&(start, end, count)| CoverageRange {
+                            start_offset: start as u32,
+                            end_offset: end as u32,
+                            count,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    const SOURCE: &str = "function f() {\n  return 1;\n}\nfunction g() {\n  return 2;\n}\nf();\n";
+
+    fn snapshot() -> ScriptCoverage {
+        let f = SOURCE.find("function f").unwrap();
+        let g = SOURCE.find("function g").unwrap();
+        script(
+            "http://localhost/app.js",
+            &[
+                ("", &[(0, SOURCE.len(), 1)]),
+                ("f", &[(f, f + 28, 1)]),
+                ("g", &[(g, g + 28, 0)]),
+            ],
+        )
+    }
+
+    #[test]
+    fn merges_snapshots_into_lcov() {
+        let mut report = CoverageReport::new();
+        report.add_script(&snapshot(), SOURCE);
+        report.add_script(&snapshot(), SOURCE);
+
+        assert_eq!(
+            "TN:\n\
+             SF:http://localhost/app.js\n\
+             FN:1,f\n\
+             FN:4,g\n\
+             FNDA:2,f\n\
+             FNDA:0,g\n\
+             FNF:2\n\
+             FNH:1\n\
+             DA:1,2\n\
+             DA:2,2\n\
+             DA:3,2\n\
+             DA:4,0\n\
+             DA:5,0\n\
+             DA:6,0\n\
+             DA:7,2\n\
+             LF:7\n\
+             LH:4\n\
+             end_of_record\n",
+            report.to_lcov()
+        );
+    }
+
+    #[test]
+    fn converts_to_istanbul() {
+        let mut report = CoverageReport::new();
+        report.add_script(&snapshot(), SOURCE);
+
+        let file = &report.to_istanbul()["http://localhost/app.js"];
+        assert_eq!("http://localhost/app.js", file["path"]);
+        assert_eq!(
+            json!({ "start": { "line": 2, "column": 2 }, "end": { "line": 2, "column": 11 } }),
+            file["statementMap"]["1"]
+        );
+        assert_eq!(json!(0), file["s"]["4"]);
+        assert_eq!("g", file["fnMap"]["1"]["name"]);
+        assert_eq!(
+            json!({ "start": { "line": 4, "column": 0 }, "end": { "line": 6, "column": 1 } }),
+            file["fnMap"]["1"]["loc"]
+        );
+        assert_eq!(json!(0), file["f"]["1"]);
+    }
+
+    #[test]
+    fn counts_offsets_in_utf16() {
+        let source = "var s = '\u{1f600}';\nif (s) {\n  never();\n}\n";
+        let utf16 = |s: &str| s.encode_utf16().count();
+        let block = utf16(&source[..source.find("{\n  never").unwrap()]);
+        let len = utf16(source);
+
+        let mut report = CoverageReport::new();
+        report.add_script(
+            &script("app.js", &[("", &[(0, len, 1), (block, block + 15, 0)])]),
+            source,
+        );
+
+        let lines = &report.files()["app.js"].lines;
+        assert_eq!(
+            vec![(1, 1), (2, 0), (3, 0), (4, 0)],
+            lines
+                .iter()
+                .map(|(&line, coverage)| (line, coverage.count))
+                .collect::<Vec<_>>()
+        );
+        // the emoji takes up two columns
+        assert_eq!(13, lines[&1].end_column);
+    }
+
+    #[test]
+    fn maps_coverage_back_to_original_sources() {
+        let source = "var a=1;\nfoo(a);";
+        let source_map = SourceMap::from_slice(
+            br#"{"version":3,"sources":["../src/app.js"],"names":["foo"],"mappings":"AAEA;AAIAA"}"#,
+            "http://localhost/dist/bundle.js",
+        )
+        .unwrap();
+
+        let mut report = CoverageReport::new();
+        report.add_mapped_script(
+            &script(
+                "http://localhost/dist/bundle.js",
+                &[("", &[(0, 16, 1)]), ("foo", &[(9, 16, 0)])],
+            ),
+            source,
+            &source_map,
+        );
+
+        assert_eq!(
+            vec!["http://localhost/src/app.js"],
+            report.files().keys().collect::<Vec<_>>()
+        );
+        let file = &report.files()["http://localhost/src/app.js"];
+        assert_eq!(
+            vec![(3, 1), (7, 0)],
+            file.lines
+                .iter()
+                .map(|(&line, coverage)| (line, coverage.count))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![("foo", Position { line: 7, column: 0 }, 0)],
+            file.functions
+                .iter()
+                .map(|function| (function.name.as_str(), function.start, function.count))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn reports_lines_of_used_css_rules() {
+        let text = "a { color: red; }\n\n.unused {\n  color: blue;\n}\n";
+        let unused = text.find(".unused").unwrap() as u32;
+        let style_sheet = |url: &str, start_line, start_column| StyleSheetCoverage {
+            style_sheet_id: "1".to_string(),
+            url: url.to_string(),
+            text: text.to_string(),
+            start_line,
+            start_column,
+            used: vec![0..17],
+            unused: vec![unused..text.len() as u32 - 1],
+        };
+
+        let mut report = CoverageReport::new();
+        report.add_css(&[
+            style_sheet("http://localhost/app.css", 0, 0),
+            style_sheet("http://localhost/index.html", 9, 11),
+            style_sheet("", 0, 0),
+        ]);
+
+        let counts = |url: &str| {
+            report.files()[url]
+                .lines
+                .iter()
+                .map(|(&line, coverage)| (line, coverage.count, coverage.start_column))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![(1, 1, 0), (3, 0, 0), (4, 0, 2), (5, 0, 0)],
+            counts("http://localhost/app.css")
+        );
+        // a `<style>` element's rules are where they are in its document
+        assert_eq!(
+            vec![(10, 1, 11), (12, 0, 0), (13, 0, 2), (14, 0, 0)],
+            counts("http://localhost/index.html")
+        );
+        assert_eq!(2, report.files().len());
+    }
+}
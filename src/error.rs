@@ -0,0 +1,232 @@
+use std::fmt;
+
+use failure::Fail;
+
+use crate::browser::tab::element::JsException;
+use crate::browser::tab::{NavigationFailed, NoElementFound};
+use crate::browser::transport::{ConnectionClosed, MethodTimeout, TargetCrashed};
+use crate::browser::{FetchError, LaunchError, PoolExhausted};
+use crate::protocol::RemoteError;
+use crate::source_map::SourceMapError;
+use crate::util::Timeout;
+
+/// Everything that can go wrong when driving a browser, as returned by `Browser`, `Tab` and
+/// `Element`, so that callers can match on what happened:
+///
+/// ```rust,no_run
+/// # use headless_chrome::{Browser, Error, LaunchOptionsBuilder};
+/// # fn main() -> Result<(), Error> {
+/// # let browser = Browser::new(LaunchOptionsBuilder::default().build().unwrap())?;
+/// # let tab = browser.wait_for_initial_tab()?;
+/// match tab.find_element("#sign-in") {
+///     Ok(element) => { element.click()?; }
+///     Err(Error::NoElementFound(_)) => println!("Already signed in"),
+///     Err(error) => return Err(error),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// No element matched a selector.
+    NoElementFound(NoElementFound),
+    /// The browser wouldn't navigate to a URL.
+    NavigationFailed(NavigationFailed),
+    /// Something we waited for, like an element appearing, didn't happen in time.
+    Timeout(Timeout),
+    /// The browser didn't answer a method call in time.
+    MethodTimeout(MethodTimeout),
+    /// The browser answered a method call with an error.
+    Remote(RemoteError),
+    /// The connection to the browser is gone, e.g. because it exited.
+    ConnectionClosed(ConnectionClosed),
+    /// The tab crashed.
+    TargetCrashed(TargetCrashed),
+    /// JavaScript evaluated in the tab threw an exception.
+    JsException(JsException),
+    /// The browser couldn't be launched.
+    Launch(LaunchError),
+    /// The browser couldn't be downloaded.
+    Fetch(FetchError),
+    /// No tab in a `BrowserPool` became available in time.
+    PoolExhausted(PoolExhausted),
+    /// A script's source map couldn't be loaded or made sense of.
+    SourceMap(SourceMapError),
+    /// Anything else, such as I/O errors or messages we couldn't make sense of.
+    Other(failure::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NoElementFound(error) => error.fmt(f),
+            Error::NavigationFailed(error) => error.fmt(f),
+            Error::Timeout(error) => error.fmt(f),
+            Error::MethodTimeout(error) => error.fmt(f),
+            Error::Remote(error) => error.fmt(f),
+            Error::ConnectionClosed(error) => error.fmt(f),
+            Error::TargetCrashed(error) => error.fmt(f),
+            Error::JsException(error) => error.fmt(f),
+            Error::Launch(error) => error.fmt(f),
+            Error::Fetch(error) => error.fmt(f),
+            Error::PoolExhausted(error) => error.fmt(f),
+            Error::SourceMap(error) => error.fmt(f),
+            Error::Other(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    /// The I/O, HTTP, protocol or other standard library error behind this one, if any.
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        let error: &dyn Fail = match self {
+            Error::NoElementFound(error) => error,
+            Error::NavigationFailed(error) => error,
+            Error::Timeout(error) => error,
+            Error::MethodTimeout(error) => error,
+            Error::Remote(error) => error,
+            Error::ConnectionClosed(error) => error,
+            Error::TargetCrashed(error) => error,
+            Error::JsException(error) => error,
+            Error::Launch(error) => error,
+            Error::Fetch(error) => error,
+            Error::PoolExhausted(error) => error,
+            Error::SourceMap(error) => error,
+            Error::Other(error) => error.as_fail(),
+        };
+        std_error(error).or_else(|| error.cause().and_then(std_error))
+    }
+}
+
+/// `error` as a standard library error, if it's one of those we wrap.
+fn std_error(error: &dyn Fail) -> Option<&(dyn std::error::Error + 'static)> {
+    macro_rules! downcast {
+        ($($error:ty),*) => {
+            $(
+                if let Some(error) = error.downcast_ref::<$error>() {
+                    return Some(error);
+                }
+            )*
+        };
+    }
+    downcast!(
+        RemoteError,
+        std::io::Error,
+        serde_json::Error,
+        base64::DecodeError,
+        reqwest::Error,
+        zip::result::ZipError
+    );
+    None
+}
+
+macro_rules! from_errors {
+    ($($variant:ident($error:ty)),*) => {
+        $(
+            impl From<$error> for Error {
+                fn from(error: $error) -> Self {
+                    Error::$variant(error)
+                }
+            }
+        )*
+
+        /// Gives errors passed around as `failure::Error` inside the crate their own variant
+        /// again, when they have one.
+        impl From<failure::Error> for Error {
+            fn from(error: failure::Error) -> Self {
+                let error = match error.downcast::<Error>() {
+                    Ok(error) => return error,
+                    Err(error) => error,
+                };
+                $(
+                    let error = match error.downcast::<$error>() {
+                        Ok(error) => return Error::$variant(error),
+                        Err(error) => error,
+                    };
+                )*
+                Error::Other(error)
+            }
+        }
+    };
+}
+
+from_errors!(
+    NoElementFound(NoElementFound),
+    NavigationFailed(NavigationFailed),
+    Timeout(Timeout),
+    MethodTimeout(MethodTimeout),
+    Remote(RemoteError),
+    ConnectionClosed(ConnectionClosed),
+    TargetCrashed(TargetCrashed),
+    JsException(JsException),
+    Launch(LaunchError),
+    Fetch(FetchError),
+    PoolExhausted(PoolExhausted),
+    SourceMap(SourceMapError)
+);
+
+macro_rules! from_other_errors {
+    ($($error:ty),*) => {
+        $(
+            impl From<$error> for Error {
+                fn from(error: $error) -> Self {
+                    Error::Other(error.into())
+                }
+            }
+        )*
+    };
+}
+
+from_other_errors!(std::io::Error, serde_json::Error, base64::DecodeError);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_typed_errors_from_failure_errors() {
+        let error: failure::Error = NoElementFound {
+            selector: "div".to_string(),
+        }
+        .into();
+        match Error::from(error) {
+            Error::NoElementFound(error) => assert_eq!("div", error.selector),
+            error => panic!("Unexpected error: {:?}", error),
+        }
+
+        let error: failure::Error = Error::Timeout(Timeout).into();
+        match Error::from(error) {
+            Error::Timeout(_) => {}
+            error => panic!("Unexpected error: {:?}", error),
+        }
+
+        let error = Error::from(failure::format_err!("Something else"));
+        assert_eq!("Something else", error.to_string());
+    }
+
+    #[test]
+    fn keeps_the_source_of_wrapped_errors() {
+        use std::error::Error as _;
+
+        let error = Error::from(std::io::Error::new(std::io::ErrorKind::Other, "disk full"));
+        assert_eq!("disk full", error.source().unwrap().to_string());
+
+        let error = Error::Remote(RemoteError {
+            code: -32000,
+            message: "Cannot navigate".to_string(),
+        });
+        assert!(error
+            .source()
+            .unwrap()
+            .downcast_ref::<RemoteError>()
+            .is_some());
+
+        let error = Error::Fetch(FetchError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no such file",
+        )));
+        assert_eq!("no such file", error.source().unwrap().to_string());
+
+        assert!(Error::Timeout(Timeout).source().is_none());
+    }
+}
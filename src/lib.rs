@@ -15,10 +15,15 @@ extern crate log;
 extern crate derive_builder;
 
 pub mod browser;
+pub mod coverage;
+mod error;
+pub mod profile;
 pub mod protocol;
+pub mod source_map;
 pub mod util;
 
 pub use browser::{Browser, LaunchOptionsBuilder, Tab};
+pub use error::Error;
 
 #[cfg(feature = "nightly")]
 #[doc(include = "../README.md")]
@@ -0,0 +1,291 @@
+//! For saving CPU profiles as `.cpuprofile` files and summing up where they spent their time.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::protocol::profiler::{Profile, ProfileNode};
+use crate::Error;
+
+/// How long a profile spent in a function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionTime {
+    /// Its name, or `(anonymous)`, or for time outside of JavaScript, e.g. `(program)`,
+    /// `(idle)` or `(garbage collector)`.
+    pub function_name: String,
+    pub url: String,
+    /// Where it starts, counting from 0.
+    pub line_number: u32,
+    pub column_number: u32,
+    /// Time spent running the function's own code.
+    pub self_time: Duration,
+    /// Time spent between calling the function and it returning, counting recursive calls once.
+    pub total_time: Duration,
+}
+
+impl Profile {
+    /// Write the profile in the JSON of a `.cpuprofile` file.
+    pub fn write<W: Write>(&self, out: W) -> Result<(), Error> {
+        serde_json::to_writer(out, self)?;
+        Ok(())
+    }
+
+    /// Save the profile as a `.cpuprofile` file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Read a profile from a `.cpuprofile` file's JSON.
+    pub fn read<R: io::Read>(json: R) -> Result<Self, Error> {
+        Ok(serde_json::from_reader(json)?)
+    }
+
+    /// The time spent in each function (of each script), most self time first, like the
+    /// Bottom-Up view in DevTools.
+    pub fn summary(&self) -> Vec<FunctionTime> {
+        let self_times = self.self_times();
+        let nodes: HashMap<u32, &ProfileNode> =
+            self.nodes.iter().map(|node| (node.id, node)).collect();
+
+        let mut summary: Vec<FunctionTime> = vec![];
+        let mut functions: HashMap<(&str, &str, u32, u32), usize> = HashMap::new();
+        let root = match self.nodes.first() {
+            Some(root) => root,
+            None => return summary,
+        };
+        let total_times = total_times(root, &nodes, &self_times);
+        // (node, whether we've been through its children, its function's index in `summary`)
+        let mut stack = vec![(root, false, None)];
+        // how many times each function is on the stack, so recursive calls count once
+        let mut active: Vec<usize> = vec![];
+        while let Some((node, visited, function)) = stack.pop() {
+            if visited {
+                if let Some(function) = function {
+                    active[function] -= 1;
+                }
+                continue;
+            }
+
+            let function = if node.id == root.id {
+                None
+            } else {
+                let frame = &node.call_frame;
+                let key = (
+                    frame.function_name.as_str(),
+                    frame.url.as_str(),
+                    frame.line_number,
+                    frame.column_number,
+                );
+                let index = *functions.entry(key).or_insert_with(|| {
+                    summary.push(FunctionTime {
+                        function_name: if frame.function_name.is_empty() {
+                            "(anonymous)".to_string()
+                        } else {
+                            frame.function_name.clone()
+                        },
+                        url: frame.url.clone(),
+                        line_number: frame.line_number,
+                        column_number: frame.column_number,
+                        self_time: Duration::default(),
+                        total_time: Duration::default(),
+                    });
+                    active.push(0);
+                    summary.len() - 1
+                });
+                let total_time = total_times.get(&node.id).copied().unwrap_or_default();
+                let time = &mut summary[index];
+                time.self_time += self_times.get(&node.id).copied().unwrap_or_default();
+                if active[index] == 0 {
+                    time.total_time += total_time;
+                }
+                active[index] += 1;
+                Some(index)
+            };
+
+            stack.push((node, true, function));
+            for child in node.children.iter().flatten() {
+                if let Some(child) = nodes.get(child) {
+                    stack.push((child, false, None));
+                }
+            }
+        }
+
+        summary.sort_by(|a, b| {
+            b.self_time
+                .cmp(&a.self_time)
+                .then(b.total_time.cmp(&a.total_time))
+        });
+        summary
+    }
+
+    /// How long each node was at the top of the stack, from the times between samples if we
+    /// have them, and otherwise its share of the profile's duration by hit count.
+    fn self_times(&self) -> HashMap<u32, Duration> {
+        let mut self_times = HashMap::new();
+        if let (Some(samples), Some(time_deltas)) = (&self.samples, &self.time_deltas) {
+            let mut timestamps = Vec::with_capacity(samples.len());
+            let mut timestamp = self.start_time;
+            for delta in time_deltas.iter().take(samples.len()) {
+                timestamp += f64::from(*delta);
+                timestamps.push(timestamp);
+            }
+            for (i, (node, start)) in samples.iter().zip(&timestamps).enumerate() {
+                let end = timestamps.get(i + 1).copied().unwrap_or(self.end_time);
+                *self_times.entry(*node).or_default() += micros(end - start);
+            }
+        } else {
+            let hits: u32 = self
+                .nodes
+                .iter()
+                .map(|node| node.hit_count.unwrap_or(0))
+                .sum();
+            if hits > 0 {
+                let interval = (self.end_time - self.start_time) / f64::from(hits);
+                for node in &self.nodes {
+                    let hit_count = node.hit_count.unwrap_or(0);
+                    self_times.insert(node.id, micros(interval * f64::from(hit_count)));
+                }
+            }
+        }
+        self_times
+    }
+}
+
+/// The time spent between calling each node and it returning.
+fn total_times(
+    root: &ProfileNode,
+    nodes: &HashMap<u32, &ProfileNode>,
+    self_times: &HashMap<u32, Duration>,
+) -> HashMap<u32, Duration> {
+    let mut totals: HashMap<u32, Duration> = HashMap::new();
+    let mut stack = vec![(root, false)];
+    while let Some((node, visited)) = stack.pop() {
+        if visited {
+            let children: Duration = node
+                .children
+                .iter()
+                .flatten()
+                .filter_map(|child| totals.get(child))
+                .sum();
+            let own = self_times.get(&node.id).copied().unwrap_or_default();
+            totals.insert(node.id, own + children);
+        } else {
+            stack.push((node, true));
+            stack.extend(
+                node.children
+                    .iter()
+                    .flatten()
+                    .filter_map(|child| nodes.get(child))
+                    .map(|child| (*child, false)),
+            );
+        }
+    }
+    totals
+}
+
+fn micros(micros: f64) -> Duration {
+    Duration::from_micros(micros.max(0.0).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn node(id: u32, function_name: &str, line_number: u32, children: &[u32]) -> serde_json::Value {
+        json!({
+            "id": id,
+            "callFrame": {
+                "functionName": function_name,
+                "scriptId": "1",
+                "url": "http://localhost/app.js",
+                "lineNumber": line_number,
+                "columnNumber": 0,
+            },
+            "children": children,
+        })
+    }
+
+    fn profile() -> Profile {
+        serde_json::from_value(json!({
+            "nodes": [
+                node(1, "(root)", 0, &[2, 5]),
+                node(2, "main", 0, &[3]),
+                node(3, "fib", 4, &[4]),
+                node(4, "fib", 4, &[]),
+                node(5, "(idle)", 0, &[]),
+            ],
+            "startTime": 1000.0,
+            "endTime": 1500.0,
+            "samples": [2, 3, 4, 4, 5],
+            "timeDeltas": [0, 100, 100, 100, 100],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn sums_up_time_by_function() {
+        let summary: Vec<_> = profile()
+            .summary()
+            .into_iter()
+            .map(|time| {
+                (
+                    time.function_name,
+                    time.self_time.as_micros(),
+                    time.total_time.as_micros(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("fib".to_string(), 300, 300),
+                ("main".to_string(), 100, 400),
+                ("(idle)".to_string(), 100, 100),
+            ],
+            summary
+        );
+    }
+
+    #[test]
+    fn falls_back_on_hit_counts() {
+        let mut profile = profile();
+        profile.samples = None;
+        profile.time_deltas = None;
+        profile.nodes[2].hit_count = Some(4);
+        profile.nodes[4].hit_count = Some(1);
+
+        let summary = profile.summary();
+        assert_eq!("fib", summary[0].function_name);
+        assert_eq!(400, summary[0].self_time.as_micros());
+        let main = summary
+            .iter()
+            .find(|time| time.function_name == "main")
+            .unwrap();
+        assert_eq!(
+            (0, 400),
+            (main.self_time.as_micros(), main.total_time.as_micros())
+        );
+    }
+
+    #[test]
+    fn writes_cpuprofile_json() {
+        let mut json = vec![];
+        profile().write(&mut json).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!("main", value["nodes"][1]["callFrame"]["functionName"]);
+        assert_eq!(json!([3]), value["nodes"][1]["children"]);
+        // optional fields are left out, rather than null
+        assert_eq!(None, value["nodes"][1].get("hitCount"));
+        assert_eq!(json!([0, 100, 100, 100, 100]), value["timeDeltas"]);
+
+        let profile = Profile::read(&json[..]).unwrap();
+        assert_eq!(5, profile.nodes.len());
+        assert_eq!(1500.0, profile.end_time);
+    }
+}
@@ -0,0 +1,104 @@
+use serde::Deserialize;
+
+/// Whether a CSS rule was used while rule usage was being tracked.
+/// See https://chromedevtools.github.io/devtools-protocol/tot/CSS#type-RuleUsage
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleUsage {
+    pub style_sheet_id: String,
+    /// Offset of the rule's start in the style sheet's text.
+    pub start_offset: f64,
+    /// Offset of the rule's end in the style sheet's text.
+    pub end_offset: f64,
+    pub used: bool,
+}
+
+/// A style sheet in a tab, as reported when it's added.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StyleSheetHeader {
+    pub style_sheet_id: String,
+    pub frame_id: String,
+    /// The URL of the style sheet, or of the document for `<style>` elements.
+    #[serde(rename = "sourceURL")]
+    pub source_url: String,
+    pub is_inline: bool,
+    /// Where the style sheet starts in the document, for `<style>` elements, counting from 0.
+    pub start_line: f64,
+    pub start_column: f64,
+}
+
+pub mod events {
+    use serde::Deserialize;
+
+    /// Fired for each style sheet the tab has when the CSS domain is enabled, and each one it
+    /// adds after.
+    #[derive(Deserialize, Debug, Clone)]
+    pub struct StyleSheetAddedEvent {
+        pub params: StyleSheetAddedParams,
+    }
+    #[derive(Deserialize, Debug, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct StyleSheetAddedParams {
+        pub header: super::StyleSheetHeader,
+    }
+}
+
+pub mod methods {
+    use crate::protocol::Method;
+    use serde::{Deserialize, Serialize};
+
+    /// Needs the DOM domain to be enabled first.
+    #[derive(Serialize, Debug)]
+    pub struct Enable {}
+    #[derive(Debug, Deserialize)]
+    pub struct EnableReturnObject {}
+    impl Method for Enable {
+        const NAME: &'static str = "CSS.enable";
+        type ReturnObject = EnableReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    pub struct Disable {}
+    #[derive(Debug, Deserialize)]
+    pub struct DisableReturnObject {}
+    impl Method for Disable {
+        const NAME: &'static str = "CSS.disable";
+        type ReturnObject = DisableReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    pub struct StartRuleUsageTracking {}
+    #[derive(Debug, Deserialize)]
+    pub struct StartRuleUsageTrackingReturnObject {}
+    impl Method for StartRuleUsageTracking {
+        const NAME: &'static str = "CSS.startRuleUsageTracking";
+        type ReturnObject = StartRuleUsageTrackingReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    pub struct StopRuleUsageTracking {}
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct StopRuleUsageTrackingReturnObject {
+        pub rule_usage: Vec<super::RuleUsage>,
+    }
+    impl Method for StopRuleUsageTracking {
+        const NAME: &'static str = "CSS.stopRuleUsageTracking";
+        type ReturnObject = StopRuleUsageTrackingReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct GetStyleSheetText<'a> {
+        pub style_sheet_id: &'a str,
+    }
+    #[derive(Debug, Deserialize)]
+    pub struct GetStyleSheetTextReturnObject {
+        pub text: String,
+    }
+    impl<'a> Method for GetStyleSheetText<'a> {
+        const NAME: &'static str = "CSS.getStyleSheetText";
+        type ReturnObject = GetStyleSheetTextReturnObject;
+    }
+}
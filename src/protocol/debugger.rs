@@ -0,0 +1,60 @@
+pub mod events {
+    use serde::Deserialize;
+
+    /// Fired for each script the tab compiles, while the debugger is enabled.
+    #[derive(Deserialize, Debug, Clone)]
+    pub struct ScriptParsedEvent {
+        pub params: ScriptParsedParams,
+    }
+    #[derive(Deserialize, Debug, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ScriptParsedParams {
+        pub script_id: String,
+        pub url: String,
+        /// Relative to `url`, or a `data:` URL for inline source maps.
+        #[serde(rename = "sourceMapURL")]
+        pub source_map_url: Option<String>,
+    }
+}
+
+pub mod methods {
+    use crate::protocol::Method;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Enable {}
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct EnableReturnObject {}
+    impl Method for Enable {
+        const NAME: &'static str = "Debugger.enable";
+        type ReturnObject = EnableReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Disable {}
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DisableReturnObject {}
+    impl Method for Disable {
+        const NAME: &'static str = "Debugger.disable";
+        type ReturnObject = DisableReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct GetScriptSource<'a> {
+        pub script_id: &'a str,
+    }
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct GetScriptSourceReturnObject {
+        pub script_source: String,
+    }
+    impl<'a> Method for GetScriptSource<'a> {
+        const NAME: &'static str = "Debugger.getScriptSource";
+        type ReturnObject = GetScriptSourceReturnObject;
+    }
+}
@@ -304,4 +304,12 @@ pub mod methods {
         type ReturnObject = GetBoxModelReturnObject;
     }
 
+    #[derive(Serialize, Debug)]
+    pub struct Enable {}
+    #[derive(Debug, Deserialize)]
+    pub struct EnableReturnObject {}
+    impl Method for Enable {
+        const NAME: &'static str = "DOM.enable";
+        type ReturnObject = EnableReturnObject;
+    }
 }
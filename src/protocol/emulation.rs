@@ -0,0 +1,21 @@
+pub mod methods {
+    use crate::protocol::Method;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SetTouchEmulationEnabled {
+        /// Whether the touch event emulation should be enabled.
+        pub enabled: bool,
+        /// Maximum touch points supported. Defaults to one.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub max_touch_points: Option<u32>,
+    }
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SetTouchEmulationEnabledReturnObject {}
+    impl Method for SetTouchEmulationEnabled {
+        const NAME: &'static str = "Emulation.setTouchEmulationEnabled";
+        type ReturnObject = SetTouchEmulationEnabledReturnObject;
+    }
+}
@@ -1,3 +1,79 @@
+use serde::Serialize;
+
+/// A mouse button, as understood by `Input.dispatchMouseEvent`.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseButton {
+    None,
+    Left,
+    Middle,
+    Right,
+}
+
+impl MouseButton {
+    /// The bit this button occupies in the `buttons` field of a mouse event.
+    pub fn bitmask(self) -> u32 {
+        match self {
+            MouseButton::None => 0,
+            MouseButton::Left => 1,
+            MouseButton::Right => 2,
+            MouseButton::Middle => 4,
+        }
+    }
+}
+
+/// A modifier key, as understood by the `modifiers` field of key and mouse events.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Modifier {
+    Alt,
+    Control,
+    Meta,
+    Shift,
+}
+
+impl Modifier {
+    /// The bit this modifier occupies in the `modifiers` field of an input event.
+    pub fn bitmask(self) -> u32 {
+        match self {
+            Modifier::Alt => 1,
+            Modifier::Control => 2,
+            Modifier::Meta => 4,
+            Modifier::Shift => 8,
+        }
+    }
+}
+
+/// A single touch point of a `Input.dispatchTouchEvent`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TouchPoint {
+    /// X coordinate of the event relative to the main frame's viewport in CSS pixels.
+    pub x: f64,
+    /// Y coordinate of the event relative to the main frame's viewport in CSS pixels.
+    pub y: f64,
+    /// X radius of the touch area.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius_x: Option<f64>,
+    /// Y radius of the touch area.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius_y: Option<f64>,
+    /// Force, from 0.0 to 1.0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub force: Option<f64>,
+    /// Identifier used to track touch sources between events, must be unique within an event.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u32>,
+}
+
+/// Which kind of input device a synthesized gesture should appear to come from.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GestureSourceType {
+    Default,
+    Touch,
+    Mouse,
+}
+
 pub mod methods {
     use crate::protocol::Method;
     use serde::{Deserialize, Serialize};
@@ -9,10 +85,22 @@ pub mod methods {
         pub event_type: &'a str,
         pub x: f64,
         pub y: f64,
+        /// Bit field representing pressed modifier keys. Alt=1, Ctrl=2, Meta/Command=4, Shift=8.
         #[serde(skip_serializing_if = "Option::is_none")]
-        pub button: Option<&'a str>,
+        pub modifiers: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub button: Option<super::MouseButton>,
+        /// Bit field representing pressed mouse buttons. Left=1, Right=2, Middle=4.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub buttons: Option<u32>,
         #[serde(skip_serializing_if = "Option::is_none")]
         pub click_count: Option<u8>,
+        /// X delta in CSS pixels for mouse wheel event.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub delta_x: Option<f64>,
+        /// Y delta in CSS pixels for mouse wheel event.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub delta_y: Option<f64>,
     }
     impl<'a> Default for DispatchMouseEvent<'a> {
         fn default() -> Self {
@@ -20,8 +108,12 @@ pub mod methods {
                 event_type: "mouseMoved",
                 x: 0.0,
                 y: 0.0,
+                modifiers: None,
                 button: None,
+                buttons: None,
                 click_count: None,
+                delta_x: None,
+                delta_y: None,
             }
         }
     }
@@ -42,10 +134,25 @@ pub mod methods {
         pub key: Option<&'a str>,
         #[serde(skip_serializing_if = "Option::is_none")]
         pub text: Option<&'a str>,
+        /// Bit field representing pressed modifier keys. Alt=1, Ctrl=2, Meta/Command=4, Shift=8.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub modifiers: Option<u32>,
         #[serde(skip_serializing_if = "Option::is_none")]
         pub code: Option<&'a str>,
         pub windows_virtual_key_code: u8,
         pub native_virtual_key_code: u8,
+        /// Whether the event was generated from auto repeat.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub auto_repeat: Option<bool>,
+        /// Whether the event was generated from the keypad.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub is_keypad: Option<bool>,
+        /// Whether the event was a system key event.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub is_system_key: Option<bool>,
+        /// Whether the event was from the left or right side of the keyboard. 1=Left, 2=Right.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub location: Option<u8>,
     }
     #[derive(Debug, Deserialize)]
     #[serde(rename_all = "camelCase")]
@@ -54,4 +161,133 @@ pub mod methods {
         const NAME: &'static str = "Input.dispatchKeyEvent";
         type ReturnObject = DispatchKeyEventReturnObject;
     }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct InsertText<'a> {
+        pub text: &'a str,
+    }
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct InsertTextReturnObject {}
+    impl<'a> Method for InsertText<'a> {
+        const NAME: &'static str = "Input.insertText";
+        type ReturnObject = InsertTextReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ImeSetComposition<'a> {
+        /// The text to insert
+        pub text: &'a str,
+        /// Selection start
+        pub selection_start: u32,
+        /// Selection end
+        pub selection_end: u32,
+        /// Replacement start
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub replacement_start: Option<u32>,
+        /// Replacement end
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub replacement_end: Option<u32>,
+    }
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ImeSetCompositionReturnObject {}
+    impl<'a> Method for ImeSetComposition<'a> {
+        const NAME: &'static str = "Input.imeSetComposition";
+        type ReturnObject = ImeSetCompositionReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DispatchTouchEvent<'a> {
+        /// One of `touchStart`, `touchMove`, `touchEnd` or `touchCancel`.
+        #[serde(rename = "type")]
+        pub event_type: &'a str,
+        /// Active touch points on the touch device. Must be empty for `touchEnd` and
+        /// `touchCancel`.
+        pub touch_points: &'a [super::TouchPoint],
+        /// Bit field representing pressed modifier keys. Alt=1, Ctrl=2, Meta/Command=4, Shift=8.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub modifiers: Option<u32>,
+    }
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DispatchTouchEventReturnObject {}
+    impl<'a> Method for DispatchTouchEvent<'a> {
+        const NAME: &'static str = "Input.dispatchTouchEvent";
+        type ReturnObject = DispatchTouchEventReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SynthesizeTapGesture {
+        pub x: f64,
+        pub y: f64,
+        /// Duration between touchdown and touchup events in ms (default: 50).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub duration: Option<u32>,
+        /// Number of times to perform the tap (e.g. 2 for double tap, default: 1).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub tap_count: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub gesture_source_type: Option<super::GestureSourceType>,
+    }
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SynthesizeTapGestureReturnObject {}
+    impl Method for SynthesizeTapGesture {
+        const NAME: &'static str = "Input.synthesizeTapGesture";
+        type ReturnObject = SynthesizeTapGestureReturnObject;
+    }
+
+    #[derive(Serialize, Debug, Default)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SynthesizeScrollGesture {
+        pub x: f64,
+        pub y: f64,
+        /// The distance to scroll along the X axis (positive to scroll left).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub x_distance: Option<f64>,
+        /// The distance to scroll along the Y axis (positive to scroll up).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub y_distance: Option<f64>,
+        /// Prevent fling (default: true).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub prevent_fling: Option<bool>,
+        /// Swipe speed in pixels per second (default: 800).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub speed: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub gesture_source_type: Option<super::GestureSourceType>,
+    }
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SynthesizeScrollGestureReturnObject {}
+    impl Method for SynthesizeScrollGesture {
+        const NAME: &'static str = "Input.synthesizeScrollGesture";
+        type ReturnObject = SynthesizeScrollGestureReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SynthesizePinchGesture {
+        pub x: f64,
+        pub y: f64,
+        /// Relative scale factor after zooming (>1.0 zooms in, <1.0 zooms out).
+        pub scale_factor: f64,
+        /// Relative pointer speed in pixels per second (default: 800).
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub relative_speed: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub gesture_source_type: Option<super::GestureSourceType>,
+    }
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SynthesizePinchGestureReturnObject {}
+    impl Method for SynthesizePinchGesture {
+        const NAME: &'static str = "Input.synthesizePinchGesture";
+        type ReturnObject = SynthesizePinchGestureReturnObject;
+    }
 }
@@ -0,0 +1,21 @@
+pub mod events {
+    use serde::Deserialize;
+
+    /// Fired when the target (e.g. a tab's renderer process) crashes.
+    #[derive(Deserialize, Debug)]
+    pub struct TargetCrashedEvent {}
+}
+
+pub mod methods {
+    use crate::protocol::Method;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Debug)]
+    pub struct Enable {}
+    #[derive(Debug, Deserialize)]
+    pub struct EnableReturnObject {}
+    impl Method for Enable {
+        const NAME: &'static str = "Inspector.enable";
+        type ReturnObject = EnableReturnObject;
+    }
+}
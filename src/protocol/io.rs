@@ -0,0 +1,39 @@
+pub mod methods {
+    use crate::protocol::Method;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Read<'a> {
+        pub handle: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub offset: Option<u32>,
+        /// The most bytes to read, which the browser picks if it isn't given.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub size: Option<u32>,
+    }
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ReadReturnObject {
+        /// Whether `data` is base64, as it is for binary streams.
+        pub base64_encoded: Option<bool>,
+        pub data: String,
+        /// Whether the end of the stream has been reached.
+        pub eof: bool,
+    }
+    impl<'a> Method for Read<'a> {
+        const NAME: &'static str = "IO.read";
+        type ReturnObject = ReadReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    pub struct Close<'a> {
+        pub handle: &'a str,
+    }
+    #[derive(Debug, Deserialize)]
+    pub struct CloseReturnObject {}
+    impl<'a> Method for Close<'a> {
+        const NAME: &'static str = "IO.close";
+        type ReturnObject = CloseReturnObject;
+    }
+}
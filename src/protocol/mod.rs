@@ -1,17 +1,24 @@
 //! For (de)serializing method calls and events from the Chrome DevTools Protocol.
 
-use failure::{Error, Fail};
+use failure::Error;
 use serde;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 pub mod browser;
+pub mod css;
+pub mod debugger;
 pub mod dom;
+pub mod emulation;
 pub mod input;
+pub mod inspector;
+pub mod io;
+pub mod network;
 pub mod page;
 pub mod profiler;
 pub mod runtime;
 pub mod target;
+pub mod tracing;
 
 pub type CallId = usize;
 
@@ -40,13 +47,23 @@ pub trait Method {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone, Fail)]
-#[fail(display = "Method call error {}: {}", code, message)]
+/// An error the browser answered a method call with.
+///
+/// A standard library error (and so a `Fail` too), so it can be the `source` of an `Error`.
+#[derive(Deserialize, Debug, PartialEq, Clone)]
 pub struct RemoteError {
     pub code: i32,
     pub message: String,
 }
 
+impl std::fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Method call error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for RemoteError {}
+
 #[derive(Deserialize, Debug, PartialEq, Clone)]
 pub struct Response {
     #[serde(rename(deserialize = "id"))]
@@ -89,6 +106,20 @@ pub enum Event {
     FrameStoppedLoading(page::events::FrameStoppedLoadingEvent),
     #[serde(rename = "Page.lifecycleEvent")]
     Lifecycle(page::events::LifecycleEvent),
+    #[serde(rename = "Inspector.targetCrashed")]
+    TargetCrashed(inspector::events::TargetCrashedEvent),
+    #[serde(rename = "CSS.styleSheetAdded")]
+    StyleSheetAdded(css::events::StyleSheetAddedEvent),
+    #[serde(rename = "Debugger.scriptParsed")]
+    ScriptParsed(debugger::events::ScriptParsedEvent),
+    #[serde(rename = "Tracing.dataCollected")]
+    DataCollected(tracing::events::DataCollectedEvent),
+    #[serde(rename = "Tracing.tracingComplete")]
+    TracingComplete(tracing::events::TracingCompleteEvent),
+    #[serde(rename = "Runtime.exceptionThrown")]
+    ExceptionThrown(Box<runtime::events::ExceptionThrownEvent>),
+    #[serde(rename = "Runtime.consoleAPICalled")]
+    ConsoleApiCalled(runtime::events::ConsoleApiCalledEvent),
 }
 
 #[derive(Deserialize, Debug)]
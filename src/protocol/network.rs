@@ -0,0 +1,13 @@
+pub mod methods {
+    use crate::protocol::Method;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Debug)]
+    pub struct ClearBrowserCookies {}
+    #[derive(Debug, Deserialize)]
+    pub struct ClearBrowserCookiesReturnObject {}
+    impl Method for ClearBrowserCookies {
+        const NAME: &'static str = "Network.clearBrowserCookies";
+        type ReturnObject = ClearBrowserCookiesReturnObject;
+    }
+}
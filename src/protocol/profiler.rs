@@ -1,8 +1,9 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use super::runtime::methods::CallFrame;
 
 // TODO: use these aliases in other parts of the protocol module
 // From experimentation, it seems the protocol's integers are i32s.
-#[allow(dead_code)]
 type JsInt = i32;
 // For when we specifically want to guard against negative numbers.
 type JsUInt = u32;
@@ -40,6 +41,52 @@ pub struct ScriptCoverage {
     pub functions: Vec<FunctionCoverage>,
 }
 
+/// How many samples were taken at a line of a function.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionTickInfo {
+    /// Counting from 1.
+    pub line: JsUInt,
+    pub ticks: JsUInt,
+}
+
+/// A function call in a CPU profile's call tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileNode {
+    pub id: JsUInt,
+    pub call_frame: CallFrame,
+    /// How many samples were taken while this call was at the top of the stack.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hit_count: Option<JsUInt>,
+    /// The IDs of the calls this one made.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<JsUInt>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deopt_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position_ticks: Option<Vec<PositionTickInfo>>,
+}
+
+/// A CPU profile, as returned by `Tab::stop_cpu_profile`, which DevTools can load (from its
+/// Performance or JavaScript Profiler panels) once saved as a `.cpuprofile` file with `save`.
+///
+/// Times are in microseconds. See `crate::profile` for saving and summing it up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    /// The call tree, starting with its root.
+    pub nodes: Vec<ProfileNode>,
+    pub start_time: f64,
+    pub end_time: f64,
+    /// The ID of the node at the top of the stack for each sample.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples: Option<Vec<JsUInt>>,
+    /// The time between each sample and the one before it (or the start of the profile).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_deltas: Option<Vec<JsInt>>,
+}
+
 pub mod methods {
     use crate::protocol::Method;
     use serde::{Deserialize, Serialize};
@@ -105,4 +152,43 @@ pub mod methods {
         const NAME: &'static str = "Profiler.takePreciseCoverage";
         type ReturnObject = TakePreciseCoverageReturnObject;
     }
+
+    /// How often to sample the stack while profiling, which has to be set before `Start`.
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SetSamplingInterval {
+        /// In microseconds.
+        pub interval: u32,
+    }
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct SetSamplingIntervalReturnObject {}
+    impl Method for SetSamplingInterval {
+        const NAME: &'static str = "Profiler.setSamplingInterval";
+        type ReturnObject = SetSamplingIntervalReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Start {}
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct StartReturnObject {}
+    impl Method for Start {
+        const NAME: &'static str = "Profiler.start";
+        type ReturnObject = StartReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Stop {}
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct StopReturnObject {
+        pub profile: super::Profile,
+    }
+    impl Method for Stop {
+        const NAME: &'static str = "Profiler.stop";
+        type ReturnObject = StopReturnObject;
+    }
 }
@@ -1,3 +1,37 @@
+pub mod events {
+    use super::methods::{ExceptionDetails, RemoteObject, StackTrace};
+    use serde::Deserialize;
+
+    /// Fired when an exception goes uncaught in the tab, while the runtime domain is enabled.
+    #[derive(Deserialize, Debug)]
+    pub struct ExceptionThrownEvent {
+        pub params: ExceptionThrownParams,
+    }
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ExceptionThrownParams {
+        pub timestamp: f64,
+        pub exception_details: ExceptionDetails,
+    }
+
+    /// Fired when the tab calls `console.log` and friends, while the runtime domain is enabled.
+    #[derive(Deserialize, Debug)]
+    pub struct ConsoleApiCalledEvent {
+        pub params: ConsoleApiCalledParams,
+    }
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ConsoleApiCalledParams {
+        /// E.g. `log`, `warning` or `error`.
+        #[serde(rename = "type")]
+        pub call_type: String,
+        pub args: Vec<RemoteObject>,
+        pub execution_context_id: u32,
+        pub timestamp: f64,
+        pub stack_trace: Option<StackTrace>,
+    }
+}
+
 pub mod methods {
     use crate::protocol::Method;
     use serde::{Deserialize, Serialize};
@@ -37,11 +71,57 @@ pub mod methods {
         pub preview: Option<ObjectPreview>,
     }
 
+    /// Detailed information about an exception (or error) that was thrown during script
+    /// compilation or execution.
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ExceptionDetails {
+        pub exception_id: u32,
+        pub text: String,
+        pub line_number: u32,
+        pub column_number: u32,
+        pub url: Option<String>,
+        pub script_id: Option<String>,
+        pub stack_trace: Option<StackTrace>,
+        pub exception: Option<RemoteObject>,
+    }
+
+    /// A function call on the stack, with its line and column counting from 0.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CallFrame {
+        pub function_name: String,
+        pub script_id: String,
+        pub url: String,
+        pub line_number: u32,
+        pub column_number: u32,
+    }
+
+    /// The stack at some point, innermost call first, followed by the one that scheduled it
+    /// for async calls.
+    #[derive(Deserialize, Debug, Clone, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct StackTrace {
+        pub description: Option<String>,
+        pub call_frames: Vec<CallFrame>,
+        pub parent: Option<Box<StackTrace>>,
+    }
+
+    /// Represents a function call argument. Only primitive, JSON-serializable values are
+    /// supported here; remote object references aren't.
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CallArgument {
+        pub value: serde_json::Value,
+    }
+
     #[derive(Serialize, Debug, Default)]
     #[serde(rename_all = "camelCase")]
     pub struct CallFunctionOn<'a> {
         pub object_id: &'a str,
         pub function_declaration: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub arguments: Option<Vec<CallArgument>>,
         pub return_by_value: bool,
         pub generate_preview: bool,
         pub silent: bool,
@@ -50,9 +130,19 @@ pub mod methods {
     #[serde(rename_all = "camelCase")]
     pub struct CallFunctionOnReturnObject {
         pub result: RemoteObject,
+        pub exception_details: Option<ExceptionDetails>,
     }
     impl<'a> Method for CallFunctionOn<'a> {
         const NAME: &'static str = "Runtime.callFunctionOn";
         type ReturnObject = CallFunctionOnReturnObject;
     }
+
+    #[derive(Serialize, Debug)]
+    pub struct Enable {}
+    #[derive(Debug, Deserialize)]
+    pub struct EnableReturnObject {}
+    impl Method for Enable {
+        const NAME: &'static str = "Runtime.enable";
+        type ReturnObject = EnableReturnObject;
+    }
 }
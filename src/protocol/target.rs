@@ -126,6 +126,19 @@ pub mod methods {
         type ReturnObject = CreateBrowserContextReturnObject;
     }
 
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DisposeBrowserContext<'a> {
+        pub browser_context_id: &'a str,
+    }
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct DisposeBrowserContextReturnObject {}
+    impl<'a> Method for DisposeBrowserContext<'a> {
+        const NAME: &'static str = "Target.disposeBrowserContext";
+        type ReturnObject = DisposeBrowserContextReturnObject;
+    }
+
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct CreateTarget<'a> {
@@ -151,6 +164,21 @@ pub mod methods {
         type ReturnObject = CreateTargetReturnObject;
     }
 
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CloseTarget<'a> {
+        pub target_id: &'a str,
+    }
+    #[derive(Deserialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CloseTargetReturnObject {
+        pub success: bool,
+    }
+    impl<'a> Method for CloseTarget<'a> {
+        const NAME: &'static str = "Target.closeTarget";
+        type ReturnObject = CloseTargetReturnObject;
+    }
+
     #[derive(Serialize)]
     #[serde(rename_all = "camelCase")]
     pub struct AttachToTarget<'a> {
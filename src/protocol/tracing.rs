@@ -0,0 +1,97 @@
+use serde::Serialize;
+
+/// What to trace, as passed to `Tab::start_tracing`.
+/// See https://chromedevtools.github.io/devtools-protocol/tot/Tracing#type-TraceConfig
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceConfig {
+    /// `recordUntilFull` (the default), `recordContinuously` or `recordAsMuchAsPossible`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_mode: Option<String>,
+    /// E.g. `devtools.timeline` or `v8.execute`, or the browser's defaults if empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub included_categories: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub excluded_categories: Vec<String>,
+}
+
+impl TraceConfig {
+    pub fn with_categories(categories: &[&str]) -> Self {
+        Self {
+            included_categories: categories
+                .iter()
+                .map(|&category| category.to_string())
+                .collect(),
+            ..Self::default()
+        }
+    }
+}
+
+/// How the browser hands over a trace once it ends.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum TransferMode {
+    /// In `Tracing.dataCollected` events, as it's recorded.
+    ReportEvents,
+    /// As a stream to read with `IO.read`, which is better for large traces.
+    ReturnAsStream,
+}
+
+pub mod events {
+    use serde::Deserialize;
+    use serde_json::Value;
+
+    /// Fired with chunks of a trace, when it's reported in events.
+    #[derive(Deserialize, Debug)]
+    pub struct DataCollectedEvent {
+        pub params: DataCollectedParams,
+    }
+    #[derive(Deserialize, Debug)]
+    pub struct DataCollectedParams {
+        pub value: Vec<Value>,
+    }
+
+    /// Fired once a trace has ended and all of it has been reported (or can be read).
+    #[derive(Deserialize, Debug)]
+    pub struct TracingCompleteEvent {
+        pub params: TracingCompleteParams,
+    }
+    #[derive(Deserialize, Debug, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct TracingCompleteParams {
+        /// Whether some of the trace was lost, because the browser's buffer filled up.
+        #[serde(default)]
+        pub data_loss_occurred: bool,
+        /// The handle of the stream to read the trace from, if it's returned as one.
+        pub stream: Option<String>,
+    }
+}
+
+pub mod methods {
+    use crate::protocol::Method;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Debug)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Start {
+        pub trace_config: super::TraceConfig,
+        pub transfer_mode: super::TransferMode,
+        /// `json` or `proto`, for streams.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub stream_format: Option<&'static str>,
+    }
+    #[derive(Debug, Deserialize)]
+    pub struct StartReturnObject {}
+    impl Method for Start {
+        const NAME: &'static str = "Tracing.start";
+        type ReturnObject = StartReturnObject;
+    }
+
+    #[derive(Serialize, Debug)]
+    pub struct End {}
+    #[derive(Debug, Deserialize)]
+    pub struct EndReturnObject {}
+    impl Method for End {
+        const NAME: &'static str = "Tracing.end";
+        type ReturnObject = EndReturnObject;
+    }
+}
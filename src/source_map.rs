@@ -0,0 +1,237 @@
+//! For finding where the code in bundled or minified scripts came from, using their source
+//! maps.
+
+use std::fs;
+
+use failure::Fail;
+use reqwest::Url;
+
+use crate::Error;
+
+#[derive(Debug, Fail)]
+pub enum SourceMapError {
+    #[fail(
+        display = "Couldn't resolve source map URL {} of {}",
+        map_url, script_url
+    )]
+    InvalidUrl { script_url: String, map_url: String },
+    #[fail(display = "Couldn't load source map from {}: {}", url, reason)]
+    Load { url: String, reason: String },
+    #[fail(display = "Invalid source map at {}: {}", url, reason)]
+    Invalid { url: String, reason: String },
+}
+
+/// Where a piece of generated code came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OriginalLocation {
+    /// The URL of the original source file, resolved against the source map's.
+    pub source: String,
+    /// Counting from 0, like the protocol's line numbers.
+    pub line: u32,
+    /// Counting from 0.
+    pub column: u32,
+    /// The original name of the identifier there, if the source map has it.
+    pub name: Option<String>,
+}
+
+/// A script's source map, as pointed to by a `//# sourceMappingURL=` comment or a `SourceMap`
+/// header, and reported by `Debugger.scriptParsed`.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    map: sourcemap::SourceMap,
+    /// The map's sources, resolved to URLs.
+    sources: Vec<String>,
+}
+
+impl SourceMap {
+    /// Parse a source map, resolving its sources against `base_url` (where it was loaded from,
+    /// or the script's URL for inline maps).
+    pub fn from_slice(json: &[u8], base_url: &str) -> Result<Self, Error> {
+        let invalid = |error: sourcemap::Error| SourceMapError::Invalid {
+            url: base_url.to_string(),
+            reason: error.to_string(),
+        };
+        let map = match sourcemap::decode_slice(json).map_err(invalid)? {
+            sourcemap::DecodedMap::Regular(map) => map,
+            sourcemap::DecodedMap::Index(index) => index.flatten().map_err(invalid)?,
+            sourcemap::DecodedMap::Hermes(hermes) => (*hermes).clone(),
+        };
+
+        let base = Url::parse(base_url).ok();
+        let sources = map
+            .sources()
+            .map(|source| {
+                base.as_ref()
+                    .and_then(|base| base.join(source).ok())
+                    .map_or_else(|| source.to_string(), |url| url.to_string())
+            })
+            .collect();
+        Ok(Self { map, sources })
+    }
+
+    /// Load the source map of the script at `script_url` from `map_url`, which is relative to
+    /// the script's URL and can be a `data:` URL (for inline maps), a `file:` URL or an HTTP(S)
+    /// one. HTTP(S) maps are fetched from this process, blocking until they arrive.
+    ///
+    /// As the page decides what `map_url` is, `file:` maps are only read for `file:` scripts.
+    pub fn load(script_url: &str, map_url: &str) -> Result<Self, Error> {
+        if map_url.starts_with("data:") {
+            return Self::from_slice(&decode_data_url(map_url)?, script_url);
+        }
+
+        let url = Url::parse(script_url)
+            .and_then(|script_url| script_url.join(map_url))
+            .or_else(|_| Url::parse(map_url))
+            .map_err(|_| SourceMapError::InvalidUrl {
+                script_url: script_url.to_string(),
+                map_url: map_url.to_string(),
+            })?;
+        let load_error = |reason: String| SourceMapError::Load {
+            url: url.to_string(),
+            reason,
+        };
+        let json = if url.scheme() == "file" {
+            if !script_url.starts_with("file:") {
+                return Err(load_error(
+                    "only file: scripts can have file: source maps".to_string(),
+                )
+                .into());
+            }
+            let path = url
+                .to_file_path()
+                .map_err(|()| load_error("not a valid path".to_string()))?;
+            fs::read(path).map_err(|error| load_error(error.to_string()))?
+        } else {
+            let mut json = vec![];
+            reqwest::get(url.as_str())
+                .and_then(reqwest::Response::error_for_status)
+                .and_then(|mut response| response.copy_to(&mut json))
+                .map_err(|error| load_error(error.to_string()))?;
+            json
+        };
+        Self::from_slice(&json, url.as_str())
+    }
+
+    /// Where the code at the given line and column of the generated script (both counting from
+    /// 0) came from, if anywhere.
+    pub fn lookup(&self, line: u32, column: u32) -> Option<OriginalLocation> {
+        let token = self.map.lookup_token(line, column)?;
+        if !token.has_source() {
+            return None;
+        }
+        Some(OriginalLocation {
+            source: self.sources[token.get_src_id() as usize].clone(),
+            line: token.get_src_line(),
+            column: token.get_src_col(),
+            name: token.get_name().map(str::to_string),
+        })
+    }
+
+    /// Each mapped position in the generated script (line and column, counting from 0), with
+    /// the index of the source it came from, and the line and column there.
+    pub(crate) fn mappings(&self) -> impl Iterator<Item = Mapping> + '_ {
+        self.map
+            .tokens()
+            .filter(sourcemap::Token::has_source)
+            .map(|token| Mapping {
+                generated: token.get_dst(),
+                source: token.get_src_id() as usize,
+                original: token.get_src(),
+            })
+    }
+
+    pub(crate) fn source(&self, index: usize) -> &str {
+        &self.sources[index]
+    }
+}
+
+pub(crate) struct Mapping {
+    pub generated: (u32, u32),
+    pub source: usize,
+    pub original: (u32, u32),
+}
+
+/// The contents of a `data:` URL, e.g. `data:application/json;base64,eyJ2ZXJzaW9uIjozfQ==`.
+fn decode_data_url(url: &str) -> Result<Vec<u8>, SourceMapError> {
+    let invalid = |reason: &str| SourceMapError::Invalid {
+        url: url.chars().take(64).collect(),
+        reason: reason.to_string(),
+    };
+    let comma = url.find(',').ok_or_else(|| invalid("no data"))?;
+    let (header, data) = (&url[..comma], &url[comma + 1..]);
+    if header.ends_with(";base64") {
+        base64::decode(data).map_err(|_| invalid("invalid base64"))
+    } else {
+        Ok(data.as_bytes().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Maps `var a=1;\nfoo(a);` (as `bundle.js`) back to lines 3 and 7 of `src/app.js`, the
+    /// latter calling `foo`.
+    const MAP: &str = r#"{
+        "version": 3,
+        "file": "bundle.js",
+        "sourceRoot": "",
+        "sources": ["../src/app.js"],
+        "names": ["foo"],
+        "mappings": "AAEA;AAIAA"
+    }"#;
+
+    #[test]
+    fn looks_up_original_locations() {
+        let map =
+            SourceMap::from_slice(MAP.as_bytes(), "http://localhost/dist/bundle.js.map").unwrap();
+        assert_eq!(
+            Some(OriginalLocation {
+                source: "http://localhost/src/app.js".to_string(),
+                line: 6,
+                column: 0,
+                name: Some("foo".to_string()),
+            }),
+            map.lookup(1, 3)
+        );
+        assert_eq!(2, map.lookup(0, 4).unwrap().line);
+    }
+
+    #[test]
+    fn loads_inline_and_file_maps() {
+        let url = format!("data:application/json;base64,{}", base64::encode(MAP));
+        let map = SourceMap::load("http://localhost/dist/bundle.js", &url).unwrap();
+        assert_eq!(
+            "http://localhost/src/app.js",
+            map.lookup(0, 0).unwrap().source
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("dist")).unwrap();
+        fs::write(dir.path().join("dist/bundle.js.map"), MAP).unwrap();
+        let script_url = Url::from_file_path(dir.path().join("dist/bundle.js")).unwrap();
+        let map = SourceMap::load(script_url.as_str(), "bundle.js.map").unwrap();
+        assert_eq!(
+            Url::from_file_path(dir.path().join("src/app.js"))
+                .unwrap()
+                .as_str(),
+            map.lookup(0, 0).unwrap().source
+        );
+
+        match SourceMap::load(script_url.as_str(), "missing.js.map") {
+            Err(Error::SourceMap(SourceMapError::Load { .. })) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn only_loads_file_maps_for_file_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("bundle.js.map"), MAP).unwrap();
+        let map_url = Url::from_file_path(dir.path().join("bundle.js.map")).unwrap();
+        match SourceMap::load("http://localhost/dist/bundle.js", map_url.as_str()) {
+            Err(Error::SourceMap(SourceMapError::Load { .. })) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
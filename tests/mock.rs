@@ -0,0 +1,825 @@
+mod logging;
+
+use failure::{format_err, Error};
+use headless_chrome::browser::transport::{DisconnectReason, MockBrowser};
+use headless_chrome::browser::{
+    BrowserPool, Isolation, PoolConfig, RestartPolicy, SupervisedBrowser,
+};
+use headless_chrome::coverage::CoverageReport;
+use headless_chrome::protocol::browser::methods::GetVersion;
+use headless_chrome::protocol::page::methods::Navigate;
+use headless_chrome::protocol::tracing::{TraceConfig, TransferMode};
+use headless_chrome::{util, Browser};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn navigates_a_mock_browser() -> Result<(), Error> {
+    logging::enable_logging();
+    let mock = MockBrowser::new();
+    mock.respond("Page.navigate", json!({ "frameId": "MAIN_FRAME" }));
+
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+    assert_eq!("about:blank", tab.get_url());
+
+    tab.navigate_to("http://example.com")?;
+
+    let navigate_call = mock
+        .calls()
+        .into_iter()
+        .find(|call| call.method == "Page.navigate")
+        .unwrap();
+    assert_eq!(json!({ "url": "http://example.com" }), navigate_call.params);
+    assert!(navigate_call.session_id.is_some());
+    Ok(())
+}
+
+#[test]
+fn unscripted_methods_fail() -> Result<(), Error> {
+    logging::enable_logging();
+    let mock = MockBrowser::new();
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+
+    let error = tab.find_element("div").unwrap_err();
+    assert_eq!(
+        "Method call error -32601: 'DOM.getDocument' wasn't found",
+        error.to_string()
+    );
+    Ok(())
+}
+
+#[test]
+fn opens_new_mock_tabs() -> Result<(), Error> {
+    logging::enable_logging();
+    let mock = MockBrowser::new();
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    browser.wait_for_initial_tab()?;
+
+    let new_tab = browser.new_tab()?;
+    assert_eq!("MOCK_TARGET_2", new_tab.get_target_id());
+    assert_eq!(2, browser.get_tabs().lock().unwrap().len());
+    Ok(())
+}
+
+#[test]
+fn calls_time_out() -> Result<(), Error> {
+    logging::enable_logging();
+    let mock = MockBrowser::new();
+    mock.never_respond("Page.navigate")
+        .never_respond("Browser.getVersion")
+        .respond("Page.reload", json!({}));
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+
+    tab.set_default_timeout(Duration::from_millis(100));
+    let error = tab.navigate_to("http://example.com").unwrap_err();
+    match error {
+        headless_chrome::Error::MethodTimeout(timeout) => {
+            assert_eq!("Page.navigate", timeout.method);
+            assert_eq!(Duration::from_millis(100), timeout.timeout);
+        }
+        error => panic!("Unexpected error: {:?}", error),
+    }
+
+    let error = tab
+        .call_method_with_timeout(
+            Navigate {
+                url: "http://example.com",
+            },
+            Duration::from_millis(10),
+        )
+        .unwrap_err();
+    assert_eq!(
+        "Method call Page.navigate timed out after 10ms",
+        error.to_string()
+    );
+
+    // the tab is still usable afterwards
+    tab.reload(false, None)?;
+
+    // when the browser doesn't pass calls on to the tab, it's still the tab's call that fails
+    mock.never_respond("Target.sendMessageToTarget");
+    let error = tab
+        .call_method_with_timeout(
+            Navigate {
+                url: "http://example.com",
+            },
+            Duration::from_millis(10),
+        )
+        .unwrap_err();
+    assert_eq!(
+        "Method call Page.navigate timed out after 10ms",
+        error.to_string()
+    );
+
+    let error = browser
+        .call_method_with_timeout(GetVersion {}, Duration::from_millis(10))
+        .unwrap_err();
+    assert_eq!(
+        "Method call Browser.getVersion timed out after 10ms",
+        error.to_string()
+    );
+    Ok(())
+}
+
+#[test]
+fn crashed_tabs_fail_calls() -> Result<(), Error> {
+    logging::enable_logging();
+    let mock = MockBrowser::new();
+    mock.never_respond("Page.navigate")
+        .respond("Page.reload", json!({}))
+        .emit_after(
+            "Page.reload",
+            json!({ "method": "Inspector.targetCrashed", "params": {} }),
+        );
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+    assert!(!tab.is_crashed());
+
+    let navigating_tab = Arc::clone(&tab);
+    let navigation = std::thread::spawn(move || {
+        navigating_tab
+            .call_method_with_timeout(
+                Navigate {
+                    url: "http://example.com",
+                },
+                Duration::from_secs(30),
+            )
+            .unwrap_err()
+    });
+    util::Wait::with_timeout(Duration::from_secs(5))
+        .until(|| {
+            mock.calls()
+                .iter()
+                .find(|call| call.method == "Page.navigate")
+                .map(|_| ())
+        })
+        .unwrap();
+
+    tab.reload(false, None)?;
+    let pending_error = navigation.join().unwrap();
+    match pending_error {
+        headless_chrome::Error::TargetCrashed(_) => {}
+        error => panic!("Unexpected error: {:?}", error),
+    }
+
+    assert!(tab.is_crashed());
+    let error = tab.reload(false, None).unwrap_err();
+    match error {
+        headless_chrome::Error::TargetCrashed(_) => {}
+        error => panic!("Unexpected error: {:?}", error),
+    }
+    Ok(())
+}
+
+#[test]
+fn reports_disconnects() -> Result<(), Error> {
+    logging::enable_logging();
+    let mock = MockBrowser::new();
+    mock.respond("Page.reload", json!({}));
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+
+    let (reasons_tx, reasons_rx) = mpsc::channel();
+    browser.on_disconnect(move |reason| reasons_tx.send(reason.clone()).unwrap());
+    mock.close_connections();
+
+    assert_eq!(
+        DisconnectReason::ConnectionClosed,
+        reasons_rx.recv_timeout(Duration::from_secs(5))?
+    );
+    let error = tab.reload(false, None).unwrap_err();
+    match error {
+        headless_chrome::Error::ConnectionClosed(closed) => {
+            assert_eq!(DisconnectReason::ConnectionClosed, closed.reason)
+        }
+        error => panic!("Unexpected error: {:?}", error),
+    }
+
+    // late callbacks are called right away
+    let (reasons_tx, reasons_rx) = mpsc::channel();
+    browser.on_disconnect(move |reason| reasons_tx.send(reason.clone()).unwrap());
+    assert!(reasons_rx.try_recv().is_ok());
+    Ok(())
+}
+
+fn navigated_to(target_id: &str, url: &str) -> serde_json::Value {
+    json!({
+        "method": "Target.targetInfoChanged",
+        "params": {
+            "targetInfo": {
+                "targetId": target_id,
+                "type": "page",
+                "title": "",
+                "url": url,
+                "attached": true
+            }
+        }
+    })
+}
+
+#[test]
+fn supervisor_restarts_disconnected_browsers() -> Result<(), Error> {
+    logging::enable_logging();
+    let mocks = Arc::new(Mutex::new(vec![]));
+    let launched_mocks = Arc::clone(&mocks);
+    let supervisor = SupervisedBrowser::with_launcher(
+        move || {
+            let mock = MockBrowser::new();
+            mock.respond("Page.navigate", json!({ "frameId": "MAIN_FRAME" }))
+                .emit_after(
+                    "Page.navigate",
+                    navigated_to("MOCK_TARGET_1", "http://example.com/"),
+                );
+            launched_mocks.lock().unwrap().push(mock.clone());
+            Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))
+        },
+        RestartPolicy {
+            initial_backoff: Duration::from_millis(10),
+            ..RestartPolicy::default()
+        },
+    )?;
+    let (restarts_tx, restarts_rx) = mpsc::channel();
+    supervisor.on_restart(move |restart| restarts_tx.send(restart.clone()).unwrap());
+
+    let browser = supervisor.browser();
+    let tab = browser.wait_for_initial_tab()?;
+    tab.navigate_to("http://example.com/")?;
+    util::Wait::with_timeout(Duration::from_secs(5))
+        .until(|| Some(()).filter(|_| tab.get_url() == "http://example.com/"))?;
+    browser.new_tab()?;
+
+    mocks.lock().unwrap()[0].close_connections();
+    let restart = restarts_rx.recv_timeout(Duration::from_secs(5))?;
+    assert_eq!(DisconnectReason::ConnectionClosed, restart.reason);
+    assert_eq!(1, restart.attempts);
+    assert_eq!(
+        vec!["http://example.com/", "about:blank"],
+        restart.reopened_urls
+    );
+    assert!(restart.lost_urls.is_empty());
+    assert_eq!(1, supervisor.restart_count());
+
+    let new_browser = supervisor.browser();
+    assert!(!Arc::ptr_eq(&browser, &new_browser));
+    assert_eq!(2, new_browser.get_tabs().lock().unwrap().len());
+    let navigate_call = mocks.lock().unwrap()[1]
+        .calls()
+        .into_iter()
+        .find(|call| call.method == "Page.navigate")
+        .unwrap();
+    assert_eq!(
+        json!({ "url": "http://example.com/" }),
+        navigate_call.params
+    );
+    Ok(())
+}
+
+#[test]
+fn supervisor_gives_up_after_max_attempts() -> Result<(), Error> {
+    logging::enable_logging();
+    let mock = MockBrowser::new();
+    let launches = Arc::new(AtomicUsize::new(0));
+    let counted_launches = Arc::clone(&launches);
+    let launched_mock = mock.clone();
+    let supervisor = SupervisedBrowser::with_launcher(
+        move || {
+            if counted_launches.fetch_add(1, Ordering::SeqCst) > 0 {
+                return Err(format_err!("Chrome is gone for good").into());
+            }
+            Browser::with_connection(|messages_tx| Ok(launched_mock.connect(messages_tx)))
+        },
+        RestartPolicy {
+            initial_backoff: Duration::from_millis(10),
+            max_attempts: Some(3),
+            ..RestartPolicy::default()
+        },
+    )?;
+
+    mock.close_connections();
+    util::Wait::with_timeout(Duration::from_secs(5))
+        .until(|| Some(()).filter(|_| supervisor.has_given_up()))?;
+    assert_eq!(4, launches.load(Ordering::SeqCst));
+    assert_eq!(3, supervisor.failed_restart_count());
+    assert_eq!(0, supervisor.restart_count());
+    Ok(())
+}
+
+fn pooled_mock() -> MockBrowser {
+    let mock = MockBrowser::new();
+    mock.respond(
+        "Browser.getVersion",
+        json!({
+            "protocolVersion": "1.3",
+            "product": "MockBrowser",
+            "revision": "0",
+            "userAgent": "MockBrowser",
+            "jsVersion": "0"
+        }),
+    )
+    .respond("Page.navigate", json!({ "frameId": "MAIN_FRAME" }))
+    .respond("Network.clearBrowserCookies", json!({}));
+    mock
+}
+
+#[test]
+fn pool_leases_and_recycles_browsers() -> Result<(), Error> {
+    logging::enable_logging();
+    let mocks = Arc::new(Mutex::new(vec![]));
+    let launched_mocks = Arc::clone(&mocks);
+    let pool = BrowserPool::with_launcher(
+        move || {
+            let mock = pooled_mock();
+            launched_mocks.lock().unwrap().push(mock.clone());
+            Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))
+        },
+        PoolConfig {
+            size: 2,
+            max_uses: Some(2),
+            lease_timeout: Duration::from_millis(100),
+            ..PoolConfig::default()
+        },
+    )?;
+    assert_eq!(2, pool.idle_count());
+
+    let first = pool.lease()?;
+    let second = pool.lease()?;
+    assert!(!Arc::ptr_eq(first.browser(), second.browser()));
+    let error = pool.lease().err().unwrap();
+    match error {
+        headless_chrome::Error::PoolExhausted(exhausted) => {
+            assert_eq!(Duration::from_millis(100), exhausted.timeout)
+        }
+        error => panic!("Unexpected error: {:?}", error),
+    }
+
+    drop(first);
+    assert_eq!(1, pool.idle_count());
+    let reset_calls: Vec<_> = mocks.lock().unwrap()[1]
+        .calls()
+        .into_iter()
+        .filter(|call| call.session_id.is_some())
+        .map(|call| call.method)
+        .filter(|method| method == "Page.navigate" || method == "Network.clearBrowserCookies")
+        .collect();
+    assert_eq!(
+        vec!["Page.navigate", "Network.clearBrowserCookies"],
+        reset_calls
+    );
+
+    // the second lease of the same browser wears it out
+    drop(pool.lease()?);
+    assert_eq!(3, mocks.lock().unwrap().len());
+    assert_eq!(1, pool.idle_count());
+    drop(second);
+    Ok(())
+}
+
+#[test]
+fn pool_replaces_unhealthy_browsers() -> Result<(), Error> {
+    logging::enable_logging();
+    let mocks = Arc::new(Mutex::new(vec![]));
+    let launched_mocks = Arc::clone(&mocks);
+    let pool = BrowserPool::with_launcher(
+        move || {
+            let mock = pooled_mock();
+            launched_mocks.lock().unwrap().push(mock.clone());
+            Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))
+        },
+        PoolConfig {
+            size: 1,
+            ..PoolConfig::default()
+        },
+    )?;
+
+    mocks.lock().unwrap()[0].close_connections();
+    let tab = pool.lease()?;
+    assert_eq!(2, mocks.lock().unwrap().len());
+    tab.navigate_to("http://example.com")?;
+    Ok(())
+}
+
+#[test]
+fn pool_isolates_tabs_in_contexts() -> Result<(), Error> {
+    logging::enable_logging();
+    let mock = pooled_mock();
+    let launched_mock = mock.clone();
+    let pool = BrowserPool::with_launcher(
+        move || Browser::with_connection(|messages_tx| Ok(launched_mock.connect(messages_tx))),
+        PoolConfig {
+            size: 2,
+            isolation: Isolation::Contexts,
+            max_uses: Some(1),
+            ..PoolConfig::default()
+        },
+    )?;
+
+    let first = pool.lease()?;
+    let second = pool.lease()?;
+    assert!(Arc::ptr_eq(first.browser(), second.browser()));
+    assert_ne!(first.get_target_id(), second.get_target_id());
+    let contexts: Vec<_> = mock
+        .calls()
+        .into_iter()
+        .filter(|call| call.method == "Target.createTarget")
+        .map(|call| call.params["browserContextId"].clone())
+        .collect();
+    assert_eq!(
+        vec![json!("MOCK_CONTEXT_1"), json!("MOCK_CONTEXT_2")],
+        contexts
+    );
+
+    let first_context = first.context_id().unwrap().to_string();
+    drop(first);
+    let disposed = mock
+        .calls()
+        .into_iter()
+        .find(|call| call.method == "Target.disposeBrowserContext")
+        .unwrap();
+    assert_eq!(json!(first_context), disposed.params["browserContextId"]);
+    // the tabs in the disposed context are gone, and one in a new context is ready to lease
+    let browser = Arc::clone(second.browser());
+    util::Wait::with_timeout(Duration::from_secs(5))
+        .until(|| Some(()).filter(|_| browser.get_tabs().lock().unwrap().len() == 3))?;
+    assert_eq!(1, pool.idle_count());
+    Ok(())
+}
+
+#[test]
+fn builds_coverage_reports_from_script_sources() -> Result<(), Error> {
+    logging::enable_logging();
+    let source = "function f() {}\nf();\n";
+    let mock = MockBrowser::new();
+    mock.respond("Debugger.enable", json!({ "debuggerId": "DEBUGGER" }))
+        .respond("Debugger.disable", json!({}))
+        .respond("Debugger.getScriptSource", json!({ "scriptSource": source }))
+        .respond(
+            "Profiler.takePreciseCoverage",
+            json!({ "result": [
+                { "scriptId": "7", "url": "http://example.com/app.js", "functions": [
+                    { "functionName": "", "ranges": [{ "startOffset": 0, "endOffset": 21, "count": 1 }] },
+                    { "functionName": "f", "ranges": [{ "startOffset": 0, "endOffset": 15, "count": 1 }] },
+                ] },
+                { "scriptId": "8", "url": "", "functions": [] },
+            ] }),
+        );
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+
+    let mut report = CoverageReport::new();
+    report.add(&tab, &tab.take_precise_js_coverage()?)?;
+
+    let lcov = report.to_lcov();
+    assert!(lcov.starts_with("TN:\nSF:http://example.com/app.js\n"));
+    assert!(lcov.contains("DA:1,1\nDA:2,1\n"));
+    let methods: Vec<_> = mock
+        .calls()
+        .into_iter()
+        .filter(|call| call.method.starts_with("Debugger."))
+        .map(|call| (call.method, call.params))
+        .collect();
+    assert_eq!(
+        vec![
+            ("Debugger.enable".to_string(), json!({})),
+            (
+                "Debugger.getScriptSource".to_string(),
+                json!({ "scriptId": "7" })
+            ),
+            ("Debugger.disable".to_string(), json!({})),
+        ],
+        methods
+    );
+
+    // a debugger enabled beforehand is left that way
+    tab.enable_debugger()?;
+    report.add(&tab, &tab.take_precise_js_coverage()?)?;
+    let disables = mock
+        .calls()
+        .into_iter()
+        .filter(|call| call.method == "Debugger.disable")
+        .count();
+    assert_eq!(1, disables);
+    Ok(())
+}
+
+#[test]
+fn maps_coverage_and_stack_traces_with_source_maps() -> Result<(), Error> {
+    logging::enable_logging();
+    let source = "var a=1;\nfoo(a);";
+    let source_map = json!({
+        "version": 3,
+        "sources": ["../src/app.js"],
+        "names": ["foo"],
+        "mappings": "AAEA;AAIAA",
+    });
+    let source_map_url = format!(
+        "data:application/json;base64,{}",
+        base64::encode(&source_map.to_string())
+    );
+    let mock = MockBrowser::new();
+    mock.respond("Debugger.enable", json!({ "debuggerId": "DEBUGGER" }))
+        .emit_after(
+            "Debugger.enable",
+            json!({ "method": "Debugger.scriptParsed", "params": {
+                "scriptId": "7",
+                "url": "http://example.com/dist/bundle.js",
+                "sourceMapURL": source_map_url,
+            } }),
+        )
+        .emit_after(
+            "Debugger.enable",
+            json!({ "method": "Debugger.scriptParsed", "params": {
+                "scriptId": "8",
+                "url": "http://example.com/broken.js",
+                "sourceMapURL": "data:application/json;base64,!!!",
+            } }),
+        )
+        .respond("Debugger.disable", json!({}))
+        .respond("Debugger.getScriptSource", json!({ "scriptSource": source }))
+        .respond(
+            "Profiler.takePreciseCoverage",
+            json!({ "result": [
+                { "scriptId": "7", "url": "http://example.com/dist/bundle.js", "functions": [
+                    { "functionName": "", "ranges": [{ "startOffset": 0, "endOffset": 16, "count": 1 }] },
+                ] },
+            ] }),
+        )
+        .respond("Page.navigate", json!({ "frameId": "MAIN_FRAME" }))
+        .emit_after(
+            "Page.navigate",
+            json!({ "method": "Page.frameNavigated", "params": { "frame": {
+                "id": "MAIN_FRAME",
+                "loaderId": "LOADER",
+                "url": "http://example.com/other",
+                "securityOrigin": "http://example.com",
+                "mimeType": "text/html",
+            } } }),
+        );
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+
+    let mut report = CoverageReport::new();
+    report.add(&tab, &tab.take_precise_js_coverage()?)?;
+    assert_eq!(
+        "TN:\nSF:http://example.com/src/app.js\nFNF:0\nFNH:0\nDA:3,1\nDA:7,1\nLF:2\nLH:2\nend_of_record\n",
+        report.to_lcov()
+    );
+
+    let stack_trace = serde_json::from_value(json!({ "callFrames": [
+        { "functionName": "foo", "scriptId": "7", "url": "http://example.com/dist/bundle.js",
+          "lineNumber": 1, "columnNumber": 3 },
+        { "functionName": "", "scriptId": "8", "url": "http://example.com/broken.js",
+          "lineNumber": 0, "columnNumber": 1 },
+        { "functionName": "", "scriptId": "9", "url": "http://example.com/other.js",
+          "lineNumber": 4, "columnNumber": 2 },
+    ] }))?;
+    // a map which can't be loaded only leaves its own frames unmapped
+    util::Wait::with_timeout(Duration::from_secs(5)).until(|| tab.get_source_map("8").err())?;
+    let stack_trace = tab.original_stack_trace(&stack_trace);
+    let frames: Vec<_> = stack_trace
+        .call_frames
+        .iter()
+        .map(|frame| (frame.url.as_str(), frame.line_number, frame.column_number))
+        .collect();
+    assert_eq!(
+        vec![
+            ("http://example.com/src/app.js", 6, 0),
+            ("http://example.com/broken.js", 0, 1),
+            ("http://example.com/other.js", 4, 2),
+        ],
+        frames
+    );
+
+    // the scripts of the previous page are forgotten
+    tab.navigate_to("http://example.com/other")?;
+    util::Wait::with_timeout(Duration::from_secs(5)).until(|| match tab.get_source_map("7") {
+        Ok(None) => Some(()),
+        _ => None,
+    })?;
+    Ok(())
+}
+
+#[test]
+fn tracks_css_rule_usage() -> Result<(), Error> {
+    logging::enable_logging();
+    let text = "a { color: red; }\n.unused { color: blue; }\n";
+    let mock = MockBrowser::new();
+    mock.respond("DOM.enable", json!({}))
+        .respond("CSS.enable", json!({}))
+        .emit_after(
+            "CSS.enable",
+            json!({ "method": "CSS.styleSheetAdded", "params": { "header": {
+                "styleSheetId": "42.1",
+                "frameId": "MAIN_FRAME",
+                "sourceURL": "http://example.com/app.css",
+                "origin": "regular",
+                "title": "",
+                "disabled": false,
+                "isInline": false,
+                "startLine": 0,
+                "startColumn": 0,
+                "length": 43,
+            } } }),
+        )
+        .respond("CSS.startRuleUsageTracking", json!({}))
+        .respond(
+            "CSS.stopRuleUsageTracking",
+            json!({ "ruleUsage": [
+                { "styleSheetId": "42.1", "startOffset": 0, "endOffset": 17, "used": true },
+                { "styleSheetId": "42.1", "startOffset": 18, "endOffset": 42, "used": false },
+            ] }),
+        )
+        .respond("CSS.getStyleSheetText", json!({ "text": text }))
+        .respond("CSS.disable", json!({}));
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+
+    tab.start_css_coverage()?;
+    let coverage = tab.stop_css_coverage()?;
+    assert_eq!(1, coverage.len());
+    assert_eq!("http://example.com/app.css", coverage[0].url);
+    assert_eq!(text, coverage[0].text);
+    assert_eq!(vec![0..17], coverage[0].used);
+    assert_eq!(vec![18..42], coverage[0].unused);
+
+    let mut report = CoverageReport::new();
+    report.add_css(&coverage);
+    assert!(report
+        .to_lcov()
+        .contains("SF:http://example.com/app.css\nFNF:0\nFNH:0\nDA:1,1\nDA:2,0\nLF:2\nLH:1\n"));
+    Ok(())
+}
+
+#[test]
+fn disables_css_when_css_coverage_fails() -> Result<(), Error> {
+    logging::enable_logging();
+    let mock = MockBrowser::new();
+    mock.respond(
+        "CSS.stopRuleUsageTracking",
+        json!({ "ruleUsage": [
+            { "styleSheetId": "42.1", "startOffset": 0, "endOffset": 17, "used": true },
+        ] }),
+    )
+    .respond("CSS.disable", json!({}));
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+
+    // the style sheet's text can't be had
+    assert!(tab.stop_css_coverage().is_err());
+    assert!(mock.calls().iter().any(|call| call.method == "CSS.disable"));
+    Ok(())
+}
+
+#[test]
+fn records_cpu_profiles() -> Result<(), Error> {
+    logging::enable_logging();
+    let frame = |name: &str| {
+        json!({ "functionName": name, "scriptId": "7", "url": "http://example.com/app.js",
+                "lineNumber": 0, "columnNumber": 0 })
+    };
+    let mock = MockBrowser::new();
+    mock.respond("Profiler.enable", json!({}))
+        .respond("Profiler.setSamplingInterval", json!({}))
+        .respond("Profiler.start", json!({}))
+        .respond(
+            "Profiler.stop",
+            json!({ "profile": {
+                "nodes": [
+                    { "id": 1, "callFrame": frame("(root)"), "hitCount": 0, "children": [2] },
+                    { "id": 2, "callFrame": frame("render"), "hitCount": 2 },
+                ],
+                "startTime": 0.0,
+                "endTime": 2000.0,
+                "samples": [2, 2],
+                "timeDeltas": [0, 1000],
+            } }),
+        );
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+
+    assert!(tab
+        .set_cpu_profile_sampling_interval(Duration::from_nanos(500))
+        .is_err());
+    tab.enable_profiler()?
+        .set_cpu_profile_sampling_interval(Duration::from_micros(50))?
+        .start_cpu_profile()?;
+    let profile = tab.stop_cpu_profile()?;
+    let summary = profile.summary();
+    assert_eq!("render", summary[0].function_name);
+    assert_eq!(Duration::from_millis(2), summary[0].self_time);
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("render.cpuprofile");
+    profile.save(&path)?;
+    let saved: serde_json::Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+    assert_eq!(json!([2, 2]), saved["samples"]);
+
+    let interval = mock
+        .calls()
+        .into_iter()
+        .find(|call| call.method == "Profiler.setSamplingInterval")
+        .map(|call| call.params);
+    assert_eq!(Some(json!({ "interval": 50 })), interval);
+    Ok(())
+}
+
+#[test]
+fn streams_traces_to_disk() -> Result<(), Error> {
+    logging::enable_logging();
+    let trace = r#"{"traceEvents":[{"name":"Layout","ph":"X","ts":1,"dur":2}]}"#;
+    let mock = MockBrowser::new();
+    mock.respond("Tracing.start", json!({}))
+        .respond("Tracing.end", json!({}))
+        .emit_after(
+            "Tracing.end",
+            json!({ "method": "Tracing.tracingComplete", "params": {
+                "dataLossOccurred": false,
+                "stream": "STREAM",
+            } }),
+        )
+        .respond(
+            "IO.read",
+            json!({ "base64Encoded": true, "data": base64::encode(trace), "eof": true }),
+        )
+        .respond("IO.close", json!({}));
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+
+    tab.start_tracing(
+        TraceConfig::with_categories(&["devtools.timeline"]),
+        TransferMode::ReturnAsStream,
+    )?;
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("trace.json");
+    tab.stop_tracing(std::fs::File::create(&path)?)?;
+    assert_eq!(trace, std::fs::read_to_string(&path)?);
+
+    let calls: Vec<_> = mock
+        .calls()
+        .into_iter()
+        .filter(|call| call.method.starts_with("Tracing.") || call.method.starts_with("IO."))
+        .map(|call| (call.method, call.params))
+        .collect();
+    assert_eq!(
+        vec![
+            (
+                "Tracing.start".to_string(),
+                json!({
+                    "traceConfig": { "includedCategories": ["devtools.timeline"] },
+                    "transferMode": "ReturnAsStream",
+                    "streamFormat": "json",
+                })
+            ),
+            ("Tracing.end".to_string(), json!({})),
+            ("IO.read".to_string(), json!({ "handle": "STREAM" })),
+            ("IO.close".to_string(), json!({ "handle": "STREAM" })),
+        ],
+        calls
+    );
+    Ok(())
+}
+
+#[test]
+fn collects_traces_reported_in_events() -> Result<(), Error> {
+    logging::enable_logging();
+    let mock = MockBrowser::new();
+    mock.respond("Tracing.start", json!({}))
+        .respond("Tracing.end", json!({}))
+        .emit_after(
+            "Tracing.end",
+            json!({ "method": "Tracing.dataCollected", "params": {
+                "value": [{ "name": "Layout", "ph": "X", "ts": 1, "dur": 2 }],
+            } }),
+        )
+        .emit_after(
+            "Tracing.end",
+            json!({ "method": "Tracing.dataCollected", "params": {
+                "value": [{ "name": "Paint", "ph": "X", "ts": 3, "dur": 1 }],
+            } }),
+        )
+        .emit_after(
+            "Tracing.end",
+            json!({ "method": "Tracing.tracingComplete", "params": { "dataLossOccurred": false } }),
+        );
+    let browser = Browser::with_connection(|messages_tx| Ok(mock.connect(messages_tx)))?;
+    let tab = browser.wait_for_initial_tab()?;
+
+    tab.start_tracing(TraceConfig::default(), TransferMode::ReportEvents)?;
+    let mut trace = vec![];
+    tab.stop_tracing(&mut trace)?;
+    let trace: serde_json::Value = serde_json::from_slice(&trace)?;
+    assert_eq!(
+        json!(["Layout", "Paint"]),
+        json!(trace["traceEvents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| &event["name"])
+            .collect::<Vec<_>>())
+    );
+    Ok(())
+}
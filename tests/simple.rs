@@ -1,8 +1,8 @@
 #![allow(unused_variables)]
 
 use headless_chrome::{
-    browser::default_executable, protocol::page::ScreenshotFormat, Browser, LaunchOptionsBuilder,
-    Tab,
+    browser::default_executable, browser::tab::Point, protocol::page::ScreenshotFormat, Browser,
+    LaunchOptionsBuilder, Tab,
 };
 use log::*;
 use rand::prelude::*;
@@ -247,3 +247,189 @@ fn find_elements() -> Result<(), failure::Error> {
     assert_eq!(8, divs.len());
     Ok(())
 }
+
+#[test]
+fn mouse_interaction() -> Result<(), failure::Error> {
+    logging::enable_logging();
+    let (_, browser, tab) = dumb_server(include_str!("mouse.html"));
+    let logged = |text: &str| -> Result<bool, failure::Error> {
+        Ok(tab
+            .wait_for_element("div#log")?
+            .get_description()?
+            .find(|n| n.node_value == text)
+            .is_some())
+    };
+
+    tab.wait_for_element("div#hover")?.hover()?;
+    assert!(logged("hovered")?);
+
+    tab.wait_for_element("div#dblclick")?.double_click()?;
+    assert!(logged("double-clicked")?);
+
+    tab.wait_for_element("div#contextmenu")?.right_click()?;
+    assert!(logged("right-clicked")?);
+
+    tab.wait_for_element("div#auxclick")?.middle_click()?;
+    assert!(logged("middle-clicked 1")?);
+
+    let target = tab.wait_for_element("div#drop-target")?;
+    tab.wait_for_element("div#drag-source")?.drag_to(&target)?;
+    assert!(logged("dropped after many moves")?);
+    assert_eq!(target.get_midpoint()?, tab.get_mouse_position());
+
+    tab.wait_for_element("div#scrollable")?
+        .scroll_wheel(0.0, 100.0)?;
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(logged("scrolled")?);
+    Ok(())
+}
+
+#[test]
+fn key_chords() -> Result<(), failure::Error> {
+    logging::enable_logging();
+    let (_, browser, tab) = dumb_server(include_str!("form.html"));
+    tab.wait_for_element("input#target")?
+        .type_into("mothership")?;
+    tab.press_chord("Control+a")?
+        .press_key("Backspace")?
+        .type_str("moon")?;
+    tab.wait_for_element("button")?.click()?;
+    let d = tab.wait_for_element("div#protocol")?.get_description()?;
+    assert!(d
+        .find(|n| n.node_value == "Missiles launched against moon")
+        .is_some());
+    Ok(())
+}
+
+#[test]
+fn unicode_and_ime_input() -> Result<(), failure::Error> {
+    logging::enable_logging();
+    let (_, browser, tab) = dumb_server(include_str!("form.html"));
+    tab.set_typing_delay(std::time::Duration::from_millis(5));
+    tab.wait_for_element("input#target")?.type_into("🚀 to ")?;
+    tab.insert_text("月")?
+        .set_composition("に", 1, 1)?
+        .set_composition("にほ", 2, 2)?
+        .commit_composition("日本")?;
+    tab.wait_for_element("button")?.click()?;
+    let d = tab.wait_for_element("div#protocol")?.get_description()?;
+    assert!(d
+        .find(|n| n.node_value == "Missiles launched against 🚀 to 月日本")
+        .is_some());
+    Ok(())
+}
+
+#[test]
+fn touch_interaction() -> Result<(), failure::Error> {
+    logging::enable_logging();
+    let (_, browser, tab) = dumb_server(include_str!("touch.html"));
+    tab.set_touch_emulation(true)?;
+    let logged = |text: &str| -> Result<bool, failure::Error> {
+        Ok(tab
+            .wait_for_element("div#log")?
+            .get_description()?
+            .find(|n| n.node_value == text)
+            .is_some())
+    };
+
+    let tappable = tab.wait_for_element("div#tappable")?;
+    tappable.tap()?;
+    assert!(logged("touched 1")?);
+    assert!(logged("tapped")?);
+
+    let midpoint = tappable.get_midpoint()?;
+    let other_finger = midpoint + Point { x: 5.0, y: 5.0 };
+    tab.touch_start(&[midpoint, other_finger])?.touch_end()?;
+    assert!(logged("touched 2")?);
+
+    let scroll_y = || -> Result<f64, failure::Error> {
+        Ok(tab
+            .find_element("body")?
+            .call_js_fn("function() { return window.scrollY; }")?
+            .value
+            .and_then(|v| v.as_f64())
+            .unwrap())
+    };
+    assert_eq!(0.0, scroll_y()?);
+    tab.swipe(Point { x: 100.0, y: 400.0 }, Point { x: 100.0, y: 100.0 })?;
+    assert!(scroll_y()? > 0.0);
+
+    tab.pinch(Point { x: 100.0, y: 100.0 }, 2.0)?;
+    Ok(())
+}
+
+#[test]
+fn form_helpers() -> Result<(), failure::Error> {
+    logging::enable_logging();
+    let (_, browser, tab) = dumb_server(include_str!("fill_form.html"));
+    let js_value =
+        |selector: &str, expression: &str| -> Result<serde_json::Value, failure::Error> {
+            Ok(tab
+                .wait_for_element(selector)?
+                .call_js_fn(&format!("function() {{ return {}; }}", expression))?
+                .value
+                .unwrap())
+        };
+
+    let languages = tab
+        .wait_for_element("select#languages")?
+        .select_options(&["de", "en", "fr"])?;
+    assert_eq!(vec!["en", "de"], languages);
+    assert!(tab
+        .wait_for_element("input#name")?
+        .select_options(&["Bob"])
+        .is_err());
+
+    tab.wait_for_element("textarea#bio")?.clear()?;
+    assert_eq!("", js_value("textarea#bio", "this.value")?);
+
+    tab.wait_for_element("input#terms")?.check()?;
+    assert_eq!(true, js_value("input#terms", "this.checked")?);
+    tab.wait_for_element("input#terms")?.uncheck()?;
+    assert_eq!(false, js_value("input#terms", "this.checked")?);
+
+    let mut fields = std::collections::HashMap::new();
+    fields.insert("input#name", "Alice");
+    fields.insert("select#country", "NZ");
+    fields.insert("input#newsletter", "false");
+    fields.insert("input#plan-free", "false");
+    fields.insert("input#plan-paid", "on");
+    fields.insert("input#size-small", "large");
+    fields.insert("input#size-large", "large");
+    tab.fill_form(&fields)?;
+
+    assert_eq!("Alice", js_value("input#name", "this.value")?);
+    assert_eq!("NZ", js_value("select#country", "this.value")?);
+    assert_eq!(false, js_value("input#newsletter", "this.checked")?);
+    assert_eq!(false, js_value("input#plan-free", "this.checked")?);
+    assert_eq!(true, js_value("input#plan-paid", "this.checked")?);
+    assert_eq!(false, js_value("input#size-small", "this.checked")?);
+    assert_eq!(true, js_value("input#size-large", "this.checked")?);
+    assert_eq!(
+        true,
+        js_value(
+            "body",
+            r#"["languages", "bio", "country", "newsletter"].every(id => changes.includes(id))"#
+        )?
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn pipe_transport() -> Result<(), failure::Error> {
+    logging::enable_logging();
+    let server = server::Server::with_dumb_html(include_str!("simple.html"));
+    let browser = Browser::new(
+        LaunchOptionsBuilder::default()
+            .path(Some(default_executable().unwrap()))
+            .remote_debugging_pipe(true)
+            .build()
+            .unwrap(),
+    )?;
+    let tab = browser.wait_for_initial_tab()?;
+    tab.navigate_to(&format!("http://127.0.0.1:{}", server.port()))?;
+    tab.wait_for_element("div#foobar")?;
+    assert!(!browser.get_version()?.product.is_empty());
+    Ok(())
+}